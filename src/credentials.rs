@@ -0,0 +1,273 @@
+// credentials of cnblog account: given without prompting, and the saved password
+use std::env;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::Deserialize;
+
+use crate::meta_weblog::crypto;
+
+pub const USERNAME_ENV: &str = "CNBLOG_USERNAME";
+pub const PASSWORD_ENV: &str = "CNBLOG_PASSWORD";
+pub const APP_KEY_ENV: &str = "CNBLOG_APP_KEY";
+
+/// obfuscated password in config directory
+const SECRET_CFG: &str = "credentials.obf";
+/// random raw key of `SECRET_CFG`, it's next to `SECRET_CFG`, so the password is only obfuscated
+/// anyone who can read config directory can read the password, only owner-only file mode protects it
+const SECRET_KEY_CFG: &str = "credentials.key";
+/// host told to credential helper
+const HELPER_HOST: &str = "rpc.cnblogs.com";
+
+/// credentials of cnblog account, every field may be missing
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct Credentials {
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub app_key: Option<String>,
+}
+
+impl Credentials {
+    /// read credentials from environment variables
+    pub fn from_env() -> Credentials {
+        let var = |name| env::var(name).ok().filter(|value: &String| !value.is_empty());
+        Credentials {
+            username: var(USERNAME_ENV),
+            password: var(PASSWORD_ENV),
+            app_key: var(APP_KEY_ENV),
+        }
+    }
+
+    /// read credentials from a TOML file with `username`, `password` and `app_key`
+    pub fn from_file(path: &Path) -> Result<Credentials, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("can't read credentials file {:?}: {}", path, e))?;
+        toml::from_str(content.as_str()).map_err(|e| format!("can't parse credentials file {:?}: {}", path, e))
+    }
+
+    /// fill missing fields by other credentials
+    pub fn or(self, other: Credentials) -> Credentials {
+        Credentials {
+            username: self.username.or(other.username),
+            password: self.password.or(other.password),
+            app_key: self.app_key.or(other.app_key),
+        }
+    }
+
+    /// names of missing fields
+    pub fn missing(&self) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        if self.username.is_none() {
+            missing.push("username");
+        }
+        if self.password.is_none() {
+            missing.push("password");
+        }
+        if self.app_key.is_none() {
+            missing.push("app_key");
+        }
+        missing
+    }
+}
+
+/// where the password of a profile is saved
+pub enum SecretStore {
+    /// obfuscated file in config directory, it's protected by file mode only
+    Obfuscated(PathBuf),
+    /// external git-credential style helper command
+    Helper(String),
+}
+
+impl SecretStore {
+    /// use the helper if it's configured, otherwise the obfuscated file
+    pub fn new(base_path: &Path, helper: Option<&str>) -> SecretStore {
+        match helper {
+            Some(helper) => SecretStore::Helper(helper.to_string()),
+            None => SecretStore::Obfuscated(base_path.to_path_buf()),
+        }
+    }
+
+    /// get saved password of user
+    pub fn get(&self, username: &str) -> Result<Option<String>, String> {
+        match self {
+            SecretStore::Obfuscated(base_path) => {
+                let path = base_path.join(SECRET_CFG);
+                if !path.exists() {
+                    return Ok(None);
+                }
+                let key = read_key(base_path.join(SECRET_KEY_CFG).as_path())?;
+                let bytes = fs::read(path.as_path()).map_err(|e| format!("can't read saved password: {}", e))?;
+                let bytes = crypto::decrypt_with_key(&bytes, &key).map_err(|e| format!("can't decode saved password: {}", e))?;
+                let secret = String::from_utf8(bytes).map_err(|e| e.to_string())?;
+                // password of another account isn't used
+                Ok(secret
+                    .split_once('\n')
+                    .filter(|(user, _)| *user == username)
+                    .map(|(_, password)| password.to_string()))
+            }
+            SecretStore::Helper(helper) => {
+                let output = run_helper(helper, "get", username, None)?;
+                Ok(output
+                    .lines()
+                    .find_map(|line| line.strip_prefix("password="))
+                    .map(|password| password.to_string()))
+            }
+        }
+    }
+
+    /// save password of user
+    pub fn store(&self, username: &str, password: &str) -> Result<(), String> {
+        match self {
+            SecretStore::Obfuscated(base_path) => {
+                let key_path = base_path.join(SECRET_KEY_CFG);
+                if !key_path.exists() {
+                    let mut key = [0u8; crypto::KEY_LEN];
+                    getrandom::getrandom(&mut key).unwrap();
+                    let key: String = key.iter().map(|b| format!("{:02x}", b)).collect();
+                    write_private(key_path.as_path(), key.as_bytes())?;
+                }
+                let key = read_key(key_path.as_path())?;
+                let secret = format!("{}\n{}", username, password);
+                let bytes = crypto::encrypt_with_key(secret.as_bytes(), &key).map_err(|e| e.to_string())?;
+                write_private(base_path.join(SECRET_CFG).as_path(), &bytes)
+            }
+            SecretStore::Helper(helper) => run_helper(helper, "store", username, Some(password)).map(|_| ()),
+        }
+    }
+
+    /// wipe saved password of user
+    pub fn erase(&self, username: &str) -> Result<(), String> {
+        match self {
+            SecretStore::Obfuscated(base_path) => {
+                for name in [SECRET_CFG, SECRET_KEY_CFG] {
+                    let path = base_path.join(name);
+                    if path.exists() {
+                        fs::remove_file(path.as_path()).map_err(|e| format!("can't remove {:?}: {}", path, e))?;
+                    }
+                }
+                Ok(())
+            }
+            SecretStore::Helper(helper) => run_helper(helper, "erase", username, None).map(|_| ()),
+        }
+    }
+}
+
+impl fmt::Display for SecretStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecretStore::Obfuscated(base_path) => write!(f, "obfuscated file {:?}", base_path.join(SECRET_CFG)),
+            SecretStore::Helper(helper) => write!(f, "credential helper `{}`", helper),
+        }
+    }
+}
+
+/// read hex key of obfuscated file
+fn read_key(path: &Path) -> Result<Vec<u8>, String> {
+    let key = fs::read_to_string(path).map_err(|e| format!("can't read key of saved password: {}", e))?;
+    let key = key.trim();
+    (0..key.len())
+        .step_by(2)
+        .map(|i| key.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(|| format!("key of saved password in {:?} is malformed", path))
+}
+
+/// write file which is only readable by owner
+fn write_private(path: &Path, content: &[u8]) -> Result<(), String> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path).map_err(|e| format!("can't write {:?}: {}", path, e))?;
+    file.write_all(content).map_err(|e| format!("can't write {:?}: {}", path, e))
+}
+
+/// run `<helper> <action>` with git credential protocol, return its output
+fn run_helper(helper: &str, action: &str, username: &str, password: Option<&str>) -> Result<String, String> {
+    // 1. run helper by shell like git
+    let command = format!("{} {}", helper, action);
+    let mut child = if cfg!(target_family = "windows") {
+        Command::new("cmd").args(["/C", command.as_str()]).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()
+    } else {
+        Command::new("sh").args(["-c", command.as_str()]).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()
+    }
+    .map_err(|e| format!("can't run credential helper `{}`: {}", helper, e))?;
+
+    // 2. write request
+    let mut request = format!("protocol=https\nhost={}\nusername={}\n", HELPER_HOST, username);
+    if let Some(password) = password {
+        request.push_str(format!("password={}\n", password).as_str());
+    }
+    request.push('\n');
+    // helper may exit without reading it, so the error is ignored
+    let _ = child.stdin.take().unwrap().write_all(request.as_bytes());
+
+    // 3. read response
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!("credential helper `{}` failed: {}", command, output.status));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_credentials() {
+        let flags = Credentials {
+            username: Some("flag".to_string()),
+            ..Default::default()
+        };
+        let file = Credentials {
+            username: Some("file".to_string()),
+            app_key: Some("key".to_string()),
+            ..Default::default()
+        };
+        let credentials = flags.or(file);
+        assert_eq!(credentials.username.as_deref(), Some("flag"));
+        assert_eq!(credentials.app_key.as_deref(), Some("key"));
+        assert_eq!(credentials.missing(), ["password"]);
+    }
+
+    #[test]
+    fn obfuscated_password() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SecretStore::new(dir.path(), None);
+        assert_eq!(store.get("user").unwrap(), None);
+
+        store.store("user", "pass").unwrap();
+        assert_eq!(store.get("user").unwrap().as_deref(), Some("pass"));
+        assert_eq!(store.get("other").unwrap(), None);
+        assert!(!fs::read(dir.path().join(SECRET_CFG)).unwrap().windows(4).any(|w| w == b"pass"));
+
+        store.erase("user").unwrap();
+        assert_eq!(store.get("user").unwrap(), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn credential_helper() {
+        let store = SecretStore::new(Path::new("."), Some("f() { if test \"$1\" = get; then echo password=secret; fi; }; f"));
+        assert_eq!(store.get("user").unwrap().as_deref(), Some("secret"));
+        store.store("user", "secret").unwrap();
+        assert!(SecretStore::new(Path::new("."), Some("false")).get("user").is_err());
+    }
+
+    #[test]
+    fn credentials_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("credentials.toml");
+        fs::write(path.as_path(), "username = \"user\"\npassword = \"pass\"\napp_key = \"key\"\n").unwrap();
+        let credentials = Credentials::from_file(path.as_path()).unwrap();
+        assert!(credentials.missing().is_empty());
+        assert!(Credentials::from_file(dir.path().join("missing").as_path()).is_err());
+    }
+}
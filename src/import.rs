@@ -0,0 +1,268 @@
+// adopt remote posts which were never managed by cnblog
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::io::{stdin, stdout, Write};
+use std::path::Path;
+
+use crate::meta_weblog::cfg::{Config, Utility};
+use crate::meta_weblog::rpc::{MetaWeblog, RpcError};
+use crate::meta_weblog::weblog::Post;
+
+/// `getRecentPosts` has no offset, so it asks for this many posts first and doubles it
+const FETCH_PAGE_SIZE: u32 = 50;
+/// stop asking for more posts after this many posts
+const FETCH_LIMIT: u32 = 5000;
+/// posts shown at once while choosing
+const SELECT_PAGE_SIZE: usize = 20;
+/// prefix of a personal category in post categories
+const CATEGORY_PREFIX: &str = "[随笔分类]";
+
+/// Adopt remote posts which are not in the blogs info database
+/// Chosen posts are saved under root path and recorded in local database
+/// posts adopted before an error are kept
+pub fn import_remote_posts(cfg: &Config, weblog: &MetaWeblog, root_path: &str, all: bool) -> Result<(), Box<dyn Error>> {
+    // 1. get unmanaged remote posts
+    let known_postids = cfg.get_local_known_postids();
+    let posts: Vec<Post> = fetch_all_posts(weblog)?
+        .into_iter()
+        .filter(|post| !post.title.starts_with("[CNBLOG]"))
+        .filter(|post| match post.postid.parse::<i32>() {
+            // only posts deleted by cnblog before can be adopted again
+            Ok(postid) => known_postids.get(&postid).copied().unwrap_or(true),
+            Err(_) => false,
+        })
+        .collect();
+    if posts.is_empty() {
+        println!("Info: all remote posts are already managed by cnblog.");
+        return Ok(());
+    }
+    println!("Info: find {} remote posts not managed by cnblog.", posts.len());
+
+    // 2. choose posts
+    let chosen = if all { posts } else { choose_posts(posts) };
+
+    // 3. save and record chosen posts
+    let mut used_paths: HashSet<String> = cfg.get_local_existed_blogs_path().into_iter().collect();
+    let categories = cfg.get_local_categories();
+    let mut new_categories = HashSet::new();
+    for post in chosen {
+        let postid: i32 = post
+            .postid
+            .parse()
+            .map_err(|e| format!("invalid postid `{}` of {}: {}", post.postid, post.title, e))?;
+        let post = match weblog.get_post(post.postid.as_str()) {
+            Ok(post) => post,
+            Err(e) => {
+                eprintln!("Warning: skip post {}, can't download it. Error: {}", postid, e);
+                continue;
+            }
+        };
+
+        // 3.1 find a free path for this post
+        let mut blog_path = blog_path_of(&post);
+        if used_paths.contains(&blog_path) || Path::new(root_path).join(&blog_path).exists() {
+            blog_path = with_postid_suffix(&blog_path, postid);
+        }
+        if used_paths.contains(&blog_path) || Path::new(root_path).join(&blog_path).exists() {
+            eprintln!("Warning: skip post {}, {} already exists.", postid, blog_path);
+            continue;
+        }
+
+        // 3.2 save blog
+        let file_path = Path::new(root_path).join(&blog_path);
+        fs::create_dir_all(file_path.parent().unwrap())
+            .and_then(|_| fs::write(file_path.as_path(), post.description.as_str()))
+            .map_err(|e| format!("can't save {}: {}", blog_path, e))?;
+        let timestamp = Utility::get_file_timestamp(file_path.as_path());
+
+        // 3.3 record blog and its category
+        if let Some(category) = category_of(&post) {
+            if !categories.contains(&category) && new_categories.insert(category.clone()) {
+                cfg.new_category(&category);
+            }
+        }
+        let hash = Utility::content_hash(post.description.as_str());
+        cfg.adopt_post(&blog_path, postid, timestamp, hash.as_str());
+        cfg.cache_post(postid, post.description.as_str());
+        println!("Adopted: {} -> {}", post.title, blog_path);
+        used_paths.insert(blog_path);
+    }
+    Ok(())
+}
+
+/// fetch all posts of the blog by asking for twice as many posts until the server has no more
+/// at most `FETCH_LIMIT` posts are fetched, older posts are dropped with a warning
+fn fetch_all_posts(weblog: &MetaWeblog) -> Result<Vec<Post>, RpcError> {
+    let mut num = FETCH_PAGE_SIZE;
+    loop {
+        let posts = weblog.get_recent_posts(num)?;
+        if (posts.len() as u32) < num {
+            return Ok(posts);
+        }
+        if num >= FETCH_LIMIT {
+            eprintln!(
+                "Warning: only the latest {} remote posts are fetched, older posts can't be imported.",
+                FETCH_LIMIT
+            );
+            return Ok(posts);
+        }
+        num = (num * 2).min(FETCH_LIMIT);
+    }
+}
+
+/// ask user which posts should be adopted, page by page
+fn choose_posts(posts: Vec<Post>) -> Vec<Post> {
+    let mut chosen = Vec::new();
+    let mut buf = String::new();
+    let total_pages = posts.len().div_ceil(SELECT_PAGE_SIZE);
+    let mut posts = posts.into_iter().peekable();
+    let mut page = 0;
+    while posts.peek().is_some() {
+        page += 1;
+        let page_posts: Vec<Post> = posts.by_ref().take(SELECT_PAGE_SIZE).collect();
+
+        // 1. print a page
+        println!("\nPage {}/{}:", page, total_pages);
+        for (i, post) in page_posts.iter().enumerate() {
+            let category = category_of(post).unwrap_or_else(|| "-".to_string());
+            println!("{:>3}. [{}] {} (postid: {})", i + 1, category, post.title, post.postid);
+        }
+
+        // 2. read choice
+        print!("Adopt which posts? (e.g. `1 3-5`, `a` all, `Enter` none, `q` stop): ");
+        stdout().flush().unwrap();
+        buf.clear();
+        stdin().read_line(&mut buf).unwrap();
+        let answer = buf.trim();
+        if answer == "q" {
+            break;
+        }
+        let indexes = if answer == "a" {
+            (0..page_posts.len()).collect()
+        } else {
+            match parse_selection(answer, page_posts.len()) {
+                Some(indexes) => indexes,
+                None => {
+                    eprintln!("Warning: can't understand `{}`, nothing is chosen on this page.", answer);
+                    continue;
+                }
+            }
+        };
+        chosen.extend(
+            page_posts
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| indexes.contains(i))
+                .map(|(_, post)| post),
+        );
+    }
+    chosen
+}
+
+/// parse selection like `1 3-5,7` to zero based indexes
+/// return None while selection is invalid
+fn parse_selection(selection: &str, len: usize) -> Option<HashSet<usize>> {
+    let mut indexes = HashSet::new();
+    for part in selection.split(|c: char| c == ',' || c.is_whitespace()) {
+        if part.is_empty() {
+            continue;
+        }
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (start.parse::<usize>().ok()?, end.parse::<usize>().ok()?),
+            None => {
+                let i = part.parse::<usize>().ok()?;
+                (i, i)
+            }
+        };
+        if start == 0 || start > end || end > len {
+            return None;
+        }
+        indexes.extend(start - 1..end);
+    }
+    Some(indexes)
+}
+
+/// get the personal category of post
+fn category_of(post: &Post) -> Option<String> {
+    post.categories
+        .iter()
+        .find_map(|category| category.strip_prefix(CATEGORY_PREFIX))
+        .map(sanitize_file_name)
+        .filter(|category| !category.is_empty())
+}
+
+/// generate local blog path (`category/title.md`) for post
+fn blog_path_of(post: &Post) -> String {
+    let mut title = sanitize_file_name(post.title.as_str());
+    if title.is_empty() {
+        title = post.postid.clone();
+    }
+    if !title.ends_with(".md") && !title.ends_with(".markdown") {
+        title.push_str(".md");
+    }
+    match category_of(post) {
+        Some(category) => format!("{}/{}", category, title),
+        None => title,
+    }
+}
+
+/// insert `-postid` before extension of blog path
+fn with_postid_suffix(blog_path: &str, postid: i32) -> String {
+    match blog_path.rsplit_once('.') {
+        Some((stem, ext)) => format!("{}-{}.{}", stem, postid, ext),
+        None => format!("{}-{}", blog_path, postid),
+    }
+}
+
+/// replace characters which can't be used in file name
+fn sanitize_file_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    name.trim().trim_start_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(title: &str, categories: &[&str]) -> Post {
+        Post {
+            postid: "42".to_string(),
+            title: title.to_string(),
+            categories: categories.iter().map(|c| c.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn blog_path_from_category_and_title() {
+        let p = post("Hello: world", &["[Markdown]", "[随笔分类]Rust"]);
+        assert_eq!(blog_path_of(&p), "Rust/Hello_ world.md");
+        let p = post("note.md", &["[Markdown]"]);
+        assert_eq!(blog_path_of(&p), "note.md");
+        let p = post("  ", &[]);
+        assert_eq!(blog_path_of(&p), "42.md");
+    }
+
+    #[test]
+    fn postid_suffix() {
+        assert_eq!(with_postid_suffix("Rust/a.md", 7), "Rust/a-7.md");
+    }
+
+    #[test]
+    fn selection() {
+        let indexes = parse_selection("1 3-4,6", 6).unwrap();
+        assert_eq!(indexes, [0, 2, 3, 5].into_iter().collect());
+        assert!(parse_selection("", 3).unwrap().is_empty());
+        assert!(parse_selection("0", 3).is_none());
+        assert!(parse_selection("2-9", 3).is_none());
+        assert!(parse_selection("x", 3).is_none());
+    }
+}
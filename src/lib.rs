@@ -7,114 +7,623 @@ use quote::quote;
 use quote::ToTokens;
 use syn::parse_macro_input;
 use syn::DataStruct;
-use syn::{Data, DeriveInput, Fields};
+use syn::{
+    Attribute, Data, DataEnum, DeriveInput, Fields, FieldsNamed, FieldsUnnamed, GenericArgument, Index, Lit, Meta,
+    NestedMeta, PathArguments, Type,
+};
 
-#[proc_macro_derive(Convert)]
+/// Convert struct from/to `xmlrpc::Value::Struct`, `TryFrom<Value>` fails with `ConvertError`
+///
+/// `#[convert(rename_all = "camelCase")]` on struct, `#[convert(rename = "...")]`, `#[convert(skip)]`
+/// and `#[convert(default = "path")]` on fields are supported.
+/// `#[convert(strict)]` on struct rejects missing members (except `Option` and default ones) and unknown members.
+/// A `BTreeMap<String, Value>` field with `#[convert(flatten)]` keeps unknown members instead.
+///
+/// A newtype struct is converted as its field, other tuple structs are converted from/to arrays.
+/// An enum of unit variants is converted from/to strings, `rename_all` and `rename` are supported
+#[proc_macro_derive(Convert, attributes(convert))]
 pub fn derive_convert(input: TokenStream) -> TokenStream {
     // input token
     let input = parse_macro_input!(input as DeriveInput);
+
+    let expanded = if !input.generics.params.is_empty() {
+        Err(syn::Error::new_spanned(&input.generics, "Convert is not implemented for generic types"))
+    } else {
+        match input.data {
+            Data::Struct(DataStruct { fields: Fields::Named(ref fields), .. }) => expand_struct(&input, fields),
+            Data::Struct(DataStruct { fields: Fields::Unnamed(ref fields), .. }) => expand_tuple_struct(&input, fields),
+            Data::Struct(DataStruct { fields: Fields::Unit, .. }) => Err(syn::Error::new_spanned(
+                &input.ident,
+                "Convert is not implemented for unit struct",
+            )),
+            Data::Enum(ref data) => expand_enum(&input, data),
+            Data::Union(ref data) => Err(syn::Error::new_spanned(
+                data.union_token,
+                "Convert is not implemented for union",
+            )),
+        }
+    };
+    expanded.unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+/// convert struct with named fields from/to `Value::Struct`
+fn expand_struct(input: &DeriveInput, fields: &FieldsNamed) -> syn::Result<TokenStream2> {
     // struct name
     let struct_name = &input.ident;
+    let target = struct_name.to_string();
+    let container_attrs = ContainerAttrs::parse(&input.attrs)?;
 
-    let expanded = match input.data {
-        // only name
-        Data::Struct(DataStruct { ref fields, .. }) => {
-            if let Fields::Named(ref fields) = fields {
-
-                // value to struct confidition
-                let confidition_implments =
-                    TokenStream2::from_iter(fields.named.iter().map(|field| {
-                        let field_name = field.ident.as_ref().unwrap();
-                        let field_type = &field.ty;
-
-                        if field_type.clone().into_token_stream().to_string() == "Vec < String >" {
-                            quote! {
-                                if k == stringify!(#field_name) {
-                                    if let Value::Array(v) = v {
-                                        for v in v.into_iter() {
-                                            if let Value::String(v) = v {
-                                                result.#field_name.push(v.clone());
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        } else if field_type.clone().into_token_stream().to_string() == "i32" {
-                            quote! {
-                                if k == stringify!(#field_name) {
-                                    if let Value::Int(v) = v {
-                                        result.#field_name = v;
-                                    }
-                                }
-                            }
-                        }else {
-                            quote! {
-                            if k == stringify!(#field_name) {
-                                if let Value::#field_type(v) = v {
-                                    result.#field_name = v.clone();
-                                    continue;
-                                }
-                            }
-                            }
-                        }
-                    }));
-                // struct to value confidition
-                let s2v_confidition = TokenStream2::from_iter(fields.named.iter().map(|field| {
-                    let field_name = field.ident.as_ref().unwrap();
-                    let field_type = &field.ty;
-
-                    // need to process extra type for 'Vec<String>'
-                    if field_type.clone().into_token_stream().to_string() == "Vec < String >" {
-                        quote! {
-                            let mut v = Vec::<Value>::new();
-                            for cate in post.#field_name.into_iter() {
-                                v.push(Value::String(cate));
-                            }
-                            hashmap.insert(stringify!(#field_name).to_string(), Value::Array(v));
-                        }
-                    } else if field_type.clone().into_token_stream().to_string() == "i32"{
-                        quote! {
-                            hashmap.insert(stringify!(#field_name).to_string(), Value::Int(post.#field_name));
-                        }
-
-                    } else {
-                        quote! {
-                            hashmap.insert(stringify!(#field_name).to_string(), Value::#field_type(post.#field_name));
-                        }
-                    }
-                }));
-                let implemented_convert = quote! {
-                    // implement convert Value to Struct
-                    impl From<Value> for #struct_name {
-                        fn from(value: Value) -> Self {
-                            let mut result = Self::default();
-                            match value {
-                                Value::Struct(value) => {
-                                    for (k, v) in value.into_iter() {
-                                        #confidition_implments
-                                    }
-                                },
-                            _ => panic!("result must be struct"),
-                            }
-                            result
-                        }
-                    }
+    let fields = fields
+        .named
+        .iter()
+        .map(|field| Ok((field, FieldAttrs::parse(&field.attrs)?)))
+        .collect::<syn::Result<Vec<_>>>()?;
+    let members = fields.iter().filter(|(_, attrs)| !attrs.skip && !attrs.flatten);
+    let mut flatten = None;
+    for (field, _) in fields.iter().filter(|(_, attrs)| attrs.flatten) {
+        if flatten.is_some() {
+            return Err(syn::Error::new_spanned(field, "only one field can be flattened"));
+        }
+        if !matches!(resolve(&field.ty)?, FieldType::Struct(inner) if matches!(resolve(inner)?, FieldType::Value)) {
+            return Err(syn::Error::new_spanned(&field.ty, "flatten field must be `BTreeMap<String, Value>`"));
+        }
+        flatten = field.ident.as_ref();
+    }
+    let member_name = |field: &syn::Field, attrs: &FieldAttrs| {
+        attrs.rename.clone().unwrap_or_else(|| {
+            container_attrs.rename(field.ident.as_ref().unwrap().to_string().as_str())
+        })
+    };
 
-                    // implement convert Struct to Value
-                    impl From<#struct_name> for Value {
-                        fn from(post: #struct_name) -> Self {
-                            let mut hashmap = BTreeMap::new();
-                            #s2v_confidition
-                            Value::Struct(hashmap)
-                        }
+    // default values of fields
+    let defaults = TokenStream2::from_iter(fields.iter().filter_map(|(field, attrs)| {
+        let field_name = field.ident.as_ref().unwrap();
+        attrs.default.as_ref().map(|default| quote! { result.#field_name = #default(); })
+    }));
+    // required members in strict mode
+    let mut required = Vec::new();
+    for (field, attrs) in members.clone() {
+        if attrs.default.is_none() && !matches!(resolve(&field.ty)?, FieldType::Option(_)) {
+            required.push(member_name(field, attrs));
+        }
+    }
+    let strict = container_attrs.strict;
+    let init_missing = if strict {
+        quote! { let mut missing: Vec<&str> = vec![#(#required),*]; }
+    } else {
+        quote! {}
+    };
+    let check_missing = if strict {
+        quote! {
+            if let Some(member) = missing.first() {
+                return Err(ConvertError::missing(#target, member.to_string()));
+            }
+        }
+    } else {
+        quote! {}
+    };
+    let unknown_member = if let Some(flatten) = flatten {
+        quote! { result.#flatten.insert(k, v); }
+    } else if strict {
+        quote! { return Err(ConvertError::unknown(#target, k)); }
+    } else {
+        quote! {}
+    };
+    // declared members win over kept unknown members
+    let flatten_to_value = match flatten {
+        Some(flatten) => quote! {
+            for (k, v) in post.#flatten.into_iter() {
+                hashmap.entry(k).or_insert(v);
+            }
+        },
+        None => quote! {},
+    };
+
+    // value to struct confidition
+    let mut confidition_implments = TokenStream2::new();
+    for (field, attrs) in members.clone() {
+        let field_name = field.ident.as_ref().unwrap();
+        let member = member_name(field, attrs);
+        let from_value = value_to_field(&field.ty, quote! { v }, &target, quote! { #member.to_string() })?;
+        let seen = if strict {
+            quote! { missing.retain(|member| *member != #member); }
+        } else {
+            quote! {}
+        };
+        confidition_implments.extend(quote! {
+            #member => {
+                #seen
+                result.#field_name = #from_value?;
+            }
+        });
+    }
+    // struct to value confidition
+    let mut s2v_confidition = TokenStream2::new();
+    for (field, attrs) in members {
+        let field_name = field.ident.as_ref().unwrap();
+        let member = member_name(field, attrs);
+
+        // None is omitted rather than sent as nil
+        if let FieldType::Option(inner) = resolve(&field.ty)? {
+            let to_value = field_to_value(inner, quote! { v })?;
+            s2v_confidition.extend(quote! {
+                if let Some(v) = post.#field_name {
+                    hashmap.insert(#member.to_string(), #to_value);
+                }
+            });
+        } else {
+            let to_value = field_to_value(&field.ty, quote! { post.#field_name })?;
+            s2v_confidition.extend(quote! {
+                hashmap.insert(#member.to_string(), #to_value);
+            });
+        }
+    }
+    Ok(quote! {
+        // implement convert Value to Struct
+        impl std::convert::TryFrom<Value> for #struct_name {
+            type Error = ConvertError;
+
+            fn try_from(value: Value) -> Result<Self, ConvertError> {
+                let mut result = Self::default();
+                #defaults
+                let value = match value {
+                    Value::Struct(value) => value,
+                    value => return Err(ConvertError::mismatch(#target, None, "struct", &value)),
+                };
+                #init_missing
+                for (k, v) in value.into_iter() {
+                    match k.as_str() {
+                        #confidition_implments
+                        _ => { #unknown_member }
                     }
+                }
+                #check_missing
+                Ok(result)
+            }
+        }
+
+        // implement convert Struct to Value
+        impl From<#struct_name> for Value {
+            fn from(post: #struct_name) -> Self {
+                let mut hashmap = BTreeMap::new();
+                #s2v_confidition
+                #flatten_to_value
+                Value::Struct(hashmap)
+            }
+        }
+    })
+}
+
+/// convert newtype struct as its field, and other tuple structs from/to `Value::Array`
+fn expand_tuple_struct(input: &DeriveInput, fields: &FieldsUnnamed) -> syn::Result<TokenStream2> {
+    let struct_name = &input.ident;
+    let target = struct_name.to_string();
+    if let Some(attr) = input
+        .attrs
+        .iter()
+        .chain(fields.unnamed.iter().flat_map(|field| field.attrs.iter()))
+        .find(|attr| attr.path.is_ident("convert"))
+    {
+        return Err(syn::Error::new_spanned(attr, "convert attributes aren't supported by tuple struct"));
+    }
+
+    let (from_value, to_value) = if fields.unnamed.len() == 1 {
+        let ty = &fields.unnamed[0].ty;
+        let from_value = value_to_field(ty, quote! { value }, &target, quote! { "0".to_string() })?;
+        let to_value = field_to_value(ty, quote! { post.0 })?;
+        (quote! { Ok(Self(#from_value?)) }, to_value)
+    } else {
+        let len = fields.unnamed.len();
+        let mut elements = Vec::new();
+        let mut values = Vec::new();
+        for (i, field) in fields.unnamed.iter().enumerate() {
+            let from_value = value_to_field(&field.ty, quote! { values.next().unwrap() }, &target, quote! { #i.to_string() })?;
+            elements.push(quote! { #from_value? });
+            let index = Index::from(i);
+            values.push(field_to_value(&field.ty, quote! { post.#index })?);
+        }
+        let from_value = quote! {
+            let values = match value {
+                Value::Array(values) => values,
+                value => return Err(ConvertError::mismatch(#target, None, "array", &value)),
+            };
+            if values.len() != #len {
+                return Err(ConvertError::invalid(
+                    #target,
+                    None,
+                    format!("expected {} elements, found {}", #len, values.len()),
+                ));
+            }
+            let mut values = values.into_iter();
+            Ok(Self(#(#elements),*))
+        };
+        (from_value, quote! { Value::Array(vec![#(#values),*]) })
+    };
+    Ok(quote! {
+        impl std::convert::TryFrom<Value> for #struct_name {
+            type Error = ConvertError;
+
+            fn try_from(value: Value) -> Result<Self, ConvertError> {
+                #from_value
+            }
+        }
+
+        impl From<#struct_name> for Value {
+            fn from(post: #struct_name) -> Self {
+                #to_value
+            }
+        }
+    })
+}
+
+/// convert enum of unit variants from/to `Value::String`
+fn expand_enum(input: &DeriveInput, data: &DataEnum) -> syn::Result<TokenStream2> {
+    let enum_name = &input.ident;
+    let target = enum_name.to_string();
+    let container_attrs = ContainerAttrs::parse(&input.attrs)?;
+    if container_attrs.strict {
+        return Err(syn::Error::new_spanned(&input.ident, "`strict` isn't supported by enum"));
+    }
+
+    let mut variants = Vec::new();
+    let mut names = Vec::new();
+    for variant in data.variants.iter() {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "Convert only supports enum of unit variants",
+            ));
+        }
+        let attrs = FieldAttrs::parse(&variant.attrs)?;
+        if attrs.skip || attrs.flatten || attrs.default.is_some() {
+            return Err(syn::Error::new_spanned(variant, "only `rename` is supported by enum variant"));
+        }
+        variants.push(&variant.ident);
+        names.push(attrs.rename.unwrap_or_else(|| container_attrs.rename(variant.ident.to_string().as_str())));
+    }
+    let expected = names.iter().map(|name| format!("`{}`", name)).collect::<Vec<_>>().join(", ");
+    Ok(quote! {
+        impl std::convert::TryFrom<Value> for #enum_name {
+            type Error = ConvertError;
+
+            fn try_from(value: Value) -> Result<Self, ConvertError> {
+                match value {
+                    Value::String(v) => match v.as_str() {
+                        #(#names => Ok(#enum_name::#variants),)*
+                        _ => Err(ConvertError::invalid(
+                            #target,
+                            None,
+                            format!("unknown variant `{}`, expected one of {}", v, #expected),
+                        )),
+                    },
+                    value => Err(ConvertError::mismatch(#target, None, "string", &value)),
+                }
+            }
+        }
+
+        impl From<#enum_name> for Value {
+            fn from(post: #enum_name) -> Self {
+                let name = match post {
+                    #(#enum_name::#variants => #names,)*
                 };
-                implemented_convert
+                Value::String(name.to_string())
+            }
+        }
+    })
+}
+
+/// `#[convert(...)]` of struct
+#[derive(Default)]
+struct ContainerAttrs {
+    rename_all: Option<String>,
+    strict: bool,
+}
+
+impl ContainerAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<ContainerAttrs> {
+        let mut container_attrs = ContainerAttrs::default();
+        for meta in convert_metas(attrs)? {
+            match meta {
+                Meta::NameValue(ref name_value) if name_value.path.is_ident("rename_all") => {
+                    let rule = lit_str(&name_value.lit)?;
+                    if !RENAME_RULES.contains(&rule.as_str()) {
+                        return Err(syn::Error::new_spanned(
+                            &name_value.lit,
+                            format!("unknown rename_all rule `{}`, expected one of {:?}", rule, RENAME_RULES),
+                        ));
+                    }
+                    container_attrs.rename_all = Some(rule);
+                }
+                Meta::Path(ref path) if path.is_ident("strict") => container_attrs.strict = true,
+                _ => return Err(syn::Error::new_spanned(&meta, "unknown convert attribute")),
+            }
+        }
+        Ok(container_attrs)
+    }
+
+    /// member name of field (snake_case) or variant (PascalCase) by `rename_all`
+    fn rename(&self, field_name: &str) -> String {
+        // split words by `_` and capital letters
+        let mut words: Vec<String> = Vec::new();
+        for (i, c) in field_name.chars().enumerate() {
+            if c == '_' {
+                words.push(String::new());
+            } else if i == 0 || c.is_uppercase() {
+                words.push(c.to_lowercase().collect());
             } else {
-                panic!("sorry, may it's a complicated struct.");
+                words.last_mut().unwrap().push(c);
+            }
+        }
+        let words: Vec<&str> = words.iter().map(|word| word.as_str()).filter(|word| !word.is_empty()).collect();
+        let capitalize = |word: &str| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        };
+        match self.rename_all.as_deref() {
+            Some("camelCase") => words
+                .iter()
+                .enumerate()
+                .map(|(i, word)| if i == 0 { word.to_string() } else { capitalize(word) })
+                .collect(),
+            Some("PascalCase") => words.iter().map(|word| capitalize(word)).collect(),
+            Some("lowercase") => words.concat(),
+            Some("kebab-case") => words.join("-"),
+            Some("snake_case") => words.join("_"),
+            _ => field_name.to_string(),
+        }
+    }
+}
+
+const RENAME_RULES: [&str; 5] = ["camelCase", "PascalCase", "lowercase", "kebab-case", "snake_case"];
+
+/// `#[convert(...)]` of field
+#[derive(Default)]
+struct FieldAttrs {
+    rename: Option<String>,
+    skip: bool,
+    flatten: bool,
+    default: Option<syn::Path>,
+}
+
+impl FieldAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<FieldAttrs> {
+        let mut field_attrs = FieldAttrs::default();
+        for meta in convert_metas(attrs)? {
+            match meta {
+                Meta::NameValue(ref name_value) if name_value.path.is_ident("rename") => {
+                    field_attrs.rename = Some(lit_str(&name_value.lit)?);
+                }
+                Meta::NameValue(ref name_value) if name_value.path.is_ident("default") => {
+                    field_attrs.default = match &name_value.lit {
+                        Lit::Str(lit) => Some(lit.parse()?),
+                        lit => return Err(syn::Error::new_spanned(lit, "expected path of default function")),
+                    };
+                }
+                Meta::Path(ref path) if path.is_ident("skip") => field_attrs.skip = true,
+                Meta::Path(ref path) if path.is_ident("flatten") => field_attrs.flatten = true,
+                _ => return Err(syn::Error::new_spanned(&meta, "unknown convert attribute")),
+            }
+        }
+        Ok(field_attrs)
+    }
+}
+
+/// items in every `#[convert(...)]`
+fn convert_metas(attrs: &[Attribute]) -> syn::Result<Vec<Meta>> {
+    let mut metas = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("convert")) {
+        match attr.parse_meta()? {
+            Meta::List(list) => {
+                for nested in list.nested {
+                    match nested {
+                        NestedMeta::Meta(meta) => metas.push(meta),
+                        NestedMeta::Lit(lit) => return Err(syn::Error::new_spanned(lit, "unexpected literal")),
+                    }
+                }
+            }
+            meta => return Err(syn::Error::new_spanned(meta, "expected `#[convert(...)]`")),
+        }
+    }
+    Ok(metas)
+}
+
+fn lit_str(lit: &Lit) -> syn::Result<String> {
+    match lit {
+        Lit::Str(lit) => Ok(lit.value()),
+        _ => Err(syn::Error::new_spanned(lit, "expected string literal")),
+    }
+}
+
+/// rust type of a field, it's resolved by type path rather than tokens
+enum FieldType<'a> {
+    /// `i32`, xmlrpc `<int>`
+    Int,
+    /// `i64`, xmlrpc `<i8>`
+    Int64,
+    Bool,
+    Double,
+    String,
+    /// `iso8601::DateTime`
+    DateTime,
+    /// `chrono::NaiveDateTime`, saved as `<dateTime.iso8601>` without timezone
+    NaiveDateTime,
+    /// `Vec<u8>`, xmlrpc `<base64>`
+    Base64,
+    /// `xmlrpc::Value` itself
+    Value,
+    /// `Vec<T>`
+    Array(&'a Type),
+    /// `BTreeMap<String, T>`
+    Struct(&'a Type),
+    /// `Option<T>`, member is omitted while it's None
+    Option(&'a Type),
+    /// another `Convert` struct
+    Nested(&'a Type),
+}
+
+/// resolve type by the last segment of its path and its generic arguments
+fn resolve(ty: &Type) -> syn::Result<FieldType<'_>> {
+    let unsupported = || {
+        syn::Error::new_spanned(
+            ty,
+            format!("unsupported field type `{}` of Convert", ty.to_token_stream()),
+        )
+    };
+    let segment = match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.segments.last().unwrap(),
+        _ => return Err(unsupported()),
+    };
+    let arguments: Vec<&Type> = match &segment.arguments {
+        PathArguments::AngleBracketed(arguments) => arguments
+            .args
+            .iter()
+            .filter_map(|argument| match argument {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    let field_type = match (segment.ident.to_string().as_str(), arguments.as_slice()) {
+        ("i32", []) => FieldType::Int,
+        ("i64", []) => FieldType::Int64,
+        ("bool", []) => FieldType::Bool,
+        ("f64", []) => FieldType::Double,
+        ("String", []) => FieldType::String,
+        ("DateTime", []) => FieldType::DateTime,
+        ("NaiveDateTime", []) => FieldType::NaiveDateTime,
+        ("Value", []) => FieldType::Value,
+        ("Vec", [inner]) if is_ident(inner, "u8") => FieldType::Base64,
+        ("Vec", [inner]) => FieldType::Array(inner),
+        ("BTreeMap", [key, inner]) if is_ident(key, "String") => FieldType::Struct(inner),
+        ("Option", [inner]) => FieldType::Option(inner),
+        // xmlrpc has no other number types
+        ("u8" | "u16" | "u32" | "u64" | "u128" | "usize" | "i8" | "i16" | "i128" | "isize" | "f32" | "char" | "str", _) => {
+            return Err(unsupported())
+        }
+        (_, []) => FieldType::Nested(ty),
+        _ => return Err(unsupported()),
+    };
+    Ok(field_type)
+}
+
+/// whether type is a bare `ident` like `u8` or `String`
+fn is_ident(ty: &Type, ident: &str) -> bool {
+    matches!(ty, Type::Path(path) if path.qself.is_none() && path.path.is_ident(ident))
+}
+
+/// expression converting `value` to `Result<ty, ConvertError>`
+/// `path` is an expression of member path used by errors
+fn value_to_field(ty: &Type, value: TokenStream2, target: &str, path: TokenStream2) -> syn::Result<TokenStream2> {
+    let simple = |variant: TokenStream2, expected: &str| {
+        quote! {
+            match #value {
+                Value::#variant(v) => Ok(v),
+                v => Err(ConvertError::mismatch(#target, Some(#path), #expected, &v)),
+            }
+        }
+    };
+    let expression = match resolve(ty)? {
+        FieldType::Int => simple(quote! { Int }, "int"),
+        FieldType::Int64 => quote! {
+            match #value {
+                Value::Int64(v) => Ok(v),
+                Value::Int(v) => Ok(v as i64),
+                v => Err(ConvertError::mismatch(#target, Some(#path), "i8", &v)),
+            }
+        },
+        FieldType::Bool => simple(quote! { Bool }, "boolean"),
+        FieldType::Double => simple(quote! { Double }, "double"),
+        FieldType::String => simple(quote! { String }, "string"),
+        FieldType::DateTime => simple(quote! { DateTime }, "dateTime.iso8601"),
+        FieldType::NaiveDateTime => quote! {
+            match #value {
+                Value::DateTime(v) => match v.date {
+                    iso8601::Date::YMD { year, month, day } => chrono::NaiveDate::from_ymd_opt(year, month, day)
+                        .and_then(|date| date.and_hms_opt(v.time.hour, v.time.minute, v.time.second)),
+                    _ => None,
+                }
+                .ok_or_else(|| ConvertError::invalid(#target, Some(#path), format!("unsupported date {}", v))),
+                v => Err(ConvertError::mismatch(#target, Some(#path), "dateTime.iso8601", &v)),
+            }
+        },
+        FieldType::Base64 => simple(quote! { Base64 }, "base64"),
+        FieldType::Value => quote! { Ok::<Value, ConvertError>(#value) },
+        FieldType::Array(inner) => {
+            let inner = value_to_field(inner, quote! { v }, target, quote! { format!("{}[{}]", #path, i) })?;
+            quote! {
+                match #value {
+                    Value::Array(v) => v.into_iter().enumerate().map(|(i, v)| #inner).collect::<Result<Vec<_>, ConvertError>>(),
+                    v => Err(ConvertError::mismatch(#target, Some(#path), "array", &v)),
+                }
+            }
+        }
+        FieldType::Struct(inner) => {
+            let inner = value_to_field(inner, quote! { v }, target, quote! { format!("{}.{}", #path, k) })?;
+            quote! {
+                match #value {
+                    Value::Struct(v) => v
+                        .into_iter()
+                        .map(|(k, v)| {
+                            let v = #inner?;
+                            Ok((k, v))
+                        })
+                        .collect::<Result<BTreeMap<String, _>, ConvertError>>(),
+                    v => Err(ConvertError::mismatch(#target, Some(#path), "struct", &v)),
+                }
+            }
+        }
+        FieldType::Nested(ty) => quote! {
+            <#ty as std::convert::TryFrom<Value>>::try_from(#value).map_err(|e| e.within(#target, #path))
+        },
+        FieldType::Option(inner) => {
+            let inner = value_to_field(inner, quote! { v }, target, path)?;
+            quote! {
+                match #value {
+                    Value::Nil => Ok(None),
+                    v => (#inner).map(Some),
+                }
+            }
+        }
+    };
+    Ok(expression)
+}
+
+/// expression converting `field` of `ty` to `Value`
+fn field_to_value(ty: &Type, field: TokenStream2) -> syn::Result<TokenStream2> {
+    let expression = match resolve(ty)? {
+        FieldType::Int => quote! { Value::Int(#field) },
+        FieldType::Int64 => quote! { Value::Int64(#field) },
+        FieldType::Bool => quote! { Value::Bool(#field) },
+        FieldType::Double => quote! { Value::Double(#field) },
+        FieldType::String => quote! { Value::String(#field) },
+        FieldType::DateTime => quote! { Value::DateTime(#field) },
+        FieldType::NaiveDateTime => quote! {
+            Value::DateTime(iso8601::datetime(#field.format("%Y-%m-%dT%H:%M:%S").to_string().as_str()).unwrap())
+        },
+        FieldType::Base64 => quote! { Value::Base64(#field) },
+        FieldType::Value => quote! { #field },
+        FieldType::Nested(_) => quote! { Value::from(#field) },
+        FieldType::Array(inner) => {
+            let inner = field_to_value(inner, quote! { v })?;
+            quote! { Value::Array(#field.into_iter().map(|v| #inner).collect()) }
+        }
+        FieldType::Struct(inner) => {
+            let inner = field_to_value(inner, quote! { v })?;
+            quote! { Value::Struct(#field.into_iter().map(|(k, v)| (k, #inner)).collect()) }
+        }
+        FieldType::Option(inner) => {
+            let inner = field_to_value(inner, quote! { v })?;
+            quote! {
+                match #field {
+                    Some(v) => #inner,
+                    None => Value::Nil,
+                }
             }
         }
-        _ => panic!("Convert is not implemented for union or enum type!"),
     };
-    expanded.into()
+    Ok(expression)
 }
@@ -1,94 +1,849 @@
 extern crate filetime;
 extern crate xmlrpc;
 
-use chrono::Datelike;
+use chrono::{Datelike, Local, TimeZone};
 use crate::meta_weblog::weblog::WpCategory;
 use crate::meta_weblog::cfg::{BLOGS_INFO_CFG, USER_INFO_CFG};
-use std::collections::{BTreeMap, HashSet};
-use std::fs::{self, create_dir};
-use std::io::{stdin, stdout, Write};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{stdin, stdout, IsTerminal, Write};
 use std::path::Path;
 use std::process::exit;
+use std::time::Duration;
 
 use chrono::Timelike;
 use walkdir::{WalkDir, DirEntry};
-use xmlrpc::Error;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use dirs::config_dir;
 
+mod credentials;
+mod import;
 mod meta_weblog;
-use meta_weblog::cfg::{BlogsInfoDO, Config, Utility};
-use meta_weblog::rpc::MetaWeblog;
+mod profile;
+mod settings;
+mod trash;
+mod verify;
+mod watch;
+
+use meta_weblog::backup;
+use meta_weblog::blob::BlobError;
+use meta_weblog::cfg::{BlogsInfoDO, Config, UserInfo, Utility};
+use meta_weblog::rpc::{MetaWeblog, RpcError};
 use meta_weblog::weblog::{BlogInfo, CategoryInfo, Post};
+use credentials::{Credentials, SecretStore};
+use settings::{DeletePolicy, Settings};
+use trash::Trash;
+
+/// environment variable of passphrase to encrypt blogs info
+const PASSPHRASE_ENV: &str = "CNBLOG_PASSPHRASE";
 
 /// It's a cnblog's blog (markdown) note synchronization tool.
 #[derive(Parser)]
 #[clap(author, version, about)]
 struct Args {
-    /// Root path of articles
-    #[clap(short, long, default_value_t = String::from("articles"))]
-    rootpath: String,
+    /// Root path of articles, it's pinned in settings.toml while initializing [default: `articles`]
+    #[clap(short, long)]
+    rootpath: Option<String>,
     
     /// Config directory of cnblog
     #[clap(short, long, default_value_t = String::from(config_dir().unwrap().join("cnblog").to_str().unwrap()))]
     config: String,
+
+    /// Profile (account) to use
+    #[clap(short, long, default_value_t = String::from(profile::DEFAULT_PROFILE))]
+    profile: String,
+
+    /// Delete remote blogs even if more than `max_delete_count` or `max_delete_percent` (settings.toml) are missing
+    #[clap(long)]
+    allow_mass_delete: bool,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+/// Without a subcommand, cnblog syncs the root path with cnblog.
+#[derive(Subcommand)]
+enum Command {
+    /// Log in to cnblog and init config without prompting
+    ///
+    /// Credentials are read from flags, CNBLOG_USERNAME/CNBLOG_PASSWORD/CNBLOG_APP_KEY, then the credentials file
+    Init {
+        /// Username of cnblog
+        #[clap(long)]
+        username: Option<String>,
+        /// Password of cnblog, it's visible to other processes, prefer CNBLOG_PASSWORD
+        #[clap(long)]
+        password: Option<String>,
+        /// App key (the name in your blog address) of cnblog
+        #[clap(long)]
+        app_key: Option<String>,
+        /// TOML file with `username`, `password` and `app_key`
+        #[clap(long)]
+        credentials: Option<String>,
+    },
+    /// Adopt remote posts which are not managed by cnblog yet
+    Import {
+        /// Adopt all unmanaged posts without asking
+        #[clap(short, long)]
+        all: bool,
+    },
+    /// Find posts edited or deleted on cnblog website directly
+    Verify {
+        /// Accept remote changes: download changed posts and move missing ones to trash
+        #[clap(long, conflicts_with = "overwrite")]
+        pull: bool,
+        /// Overwrite changed remote posts with local blogs
+        #[clap(long)]
+        overwrite: bool,
+        /// Re-create missing remote posts from local blogs
+        #[clap(long)]
+        recreate: bool,
+    },
+    /// Show local changes since last sync, it works offline by cached posts
+    Diff {
+        /// Blogs or directories relative to root path [default: all blogs]
+        paths: Vec<String>,
+    },
+    /// Keep running and sync whenever blogs are changed
+    Watch {
+        /// Seconds to wait for a burst of saves to settle
+        #[clap(long, default_value_t = 2)]
+        debounce: u64,
+        /// Seconds between checks of remote changes
+        #[clap(long, default_value_t = 60)]
+        interval: u64,
+    },
+    /// Encrypt blogs info uploaded to cnblog with a passphrase
+    Encrypt {
+        /// Upload blogs info without encryption again
+        #[clap(long)]
+        disable: bool,
+    },
+    /// Log in again after password or app key is changed, blogs info is kept
+    ///
+    /// Credentials are read from flags, CNBLOG_USERNAME/CNBLOG_PASSWORD/CNBLOG_APP_KEY, the credentials file,
+    /// then username and app key of the profile
+    Login {
+        /// Username of cnblog
+        #[clap(long)]
+        username: Option<String>,
+        /// Password of cnblog, it's visible to other processes, prefer CNBLOG_PASSWORD
+        #[clap(long)]
+        password: Option<String>,
+        /// App key (the name in your blog address) of cnblog
+        #[clap(long)]
+        app_key: Option<String>,
+        /// TOML file with `username`, `password` and `app_key`
+        #[clap(long)]
+        credentials: Option<String>,
+    },
+    /// Wipe saved password of cnblog account from the obfuscated file or the credential helper
+    Logout,
+    /// List backups of local blogs info, or restore one of them
+    RestoreDb {
+        /// Backup file (or its index in the list) to restore
+        backup: Option<String>,
+        /// Upload restored blogs info to cnblog, otherwise remote changes are synced into it later
+        #[clap(long)]
+        upload: bool,
+    },
+    /// Manage profiles, every profile has its own account and blogs info
+    Profile {
+        #[clap(subcommand)]
+        command: ProfileCommand,
+    },
+    /// Manage deleted blogs in .cnblog_deleted
+    Trash {
+        #[clap(subcommand)]
+        command: TrashCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum TrashCommand {
+    /// List deleted blogs
+    List,
+    /// Move deleted blogs back and publish them again
+    Restore {
+        /// Ids shown by `trash list`
+        #[clap(required = true)]
+        ids: Vec<u32>,
+    },
+    /// Delete blogs which were moved to trash long ago
+    Purge {
+        /// Age like `30d`, `12h` or `2w`
+        #[clap(long)]
+        older_than: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProfileCommand {
+    /// List profiles
+    List,
+    /// Add a profile and log in to its account
+    Add {
+        name: String,
+        /// Default root path of articles of this profile
+        #[clap(short, long)]
+        rootpath: Option<String>,
+    },
+    /// Remove local config of a profile, posts on cnblog are kept
+    Remove {
+        name: String,
+    },
 }
 
 fn main() {
     let args = Args::parse();
-    let base_path_str = args.config.as_str();
-    if let Err(e) = init_user_cfg(base_path_str) {
+    if let Some(Command::Profile { command }) = args.command {
+        profile_command(Path::new(args.config.as_str()), command);
+        return;
+    }
+
+    // find config directory of profile
+    if let Err(e) = profile::check_name(args.profile.as_str()) {
+        eprintln!("Error: {e}");
+        exit(1);
+    }
+    let config_path = Path::new(args.config.as_str());
+    let initializing = matches!(args.command, Some(Command::Init { .. }));
+    if !initializing && args.profile != profile::DEFAULT_PROFILE && !profile::exists(config_path, args.profile.as_str()) {
+        eprintln!(
+            "Error: profile `{}` doesn't exist, add it by `cnblog profile add {}`",
+            args.profile, args.profile
+        );
+        exit(1);
+    }
+    let base_path_buf = profile::profile_path(config_path, args.profile.as_str());
+    let base_path_str = base_path_buf.to_str().unwrap();
+    if let Some(Command::Init { username, password, app_key, credentials }) = args.command {
+        if profile::exists(config_path, args.profile.as_str()) {
+            eprintln!("Error: profile `{}` has been initialized.", args.profile);
+            exit(1);
+        }
+        let flags = Credentials { username, password, app_key };
+        if let Err(e) = init_user_cfg(base_path_str, args.rootpath.as_deref(), flags, credentials.as_deref()) {
+            eprintln!("Error: {e}");
+            exit(1);
+        }
+        println!("Info: profile `{}` is initialized.", args.profile);
+        return;
+    }
+    if let Err(e) = init_user_cfg(base_path_str, args.rootpath.as_deref(), Credentials::default(), None) {
         eprintln!("{e}");
         exit(1);
     }
 
-    let blog_root_path_str = args.rootpath.as_str();
-
-    // get user info
+    // get user info and settings
     let base_path = Path::new(base_path_str);
-    let user_info = Config::read_user_info_cfg(&base_path.join(USER_INFO_CFG)).unwrap();
+    let mut user_info = Config::read_user_info_cfg(&base_path.join(USER_INFO_CFG)).unwrap();
+    let mut settings = match Settings::load(base_path) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            exit(1);
+        }
+    };
+    if let Some(Command::Logout) = args.command {
+        logout(base_path, &settings, user_info);
+        return;
+    }
+    if let Some(Command::Login { username, password, app_key, credentials }) = args.command {
+        let flags = Credentials { username, password, app_key };
+        if let Err(e) = login(base_path, &settings, &mut user_info, flags, credentials.as_deref()) {
+            eprintln!("Error: {e}");
+            exit(1);
+        }
+        println!("Info: {} is logged in.", user_info.username);
+        return;
+    }
+    let mut password = match load_password(base_path, &settings, &user_info) {
+        Ok(password) => password,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            exit(1);
+        }
+    };
 
     // init config & weblog
-    let mut cfg = Config::new(
-        &user_info.username,
-        &user_info.password,
-        &user_info.app_key,
-        user_info.postid,
-        &user_info.blogid,
-        base_path_str,
-    );
-    cfg.init_conn(); // must call it
-    let mut weblog = MetaWeblog::new(
-        user_info.username.to_string(),
-        user_info.password.to_string(),
-        user_info.blogid.to_string(),
-        user_info.app_key.to_string(),
-    );
+    let mut cfg = new_config(base_path_str, &user_info, &password, &settings, env::var(PASSPHRASE_ENV).ok());
+    // restore database before it's synced with remote
+    if let Some(Command::RestoreDb { backup, upload }) = args.command {
+        restore_database(cfg, backup, upload);
+        return;
+    }
+
+    // check root path
+    if settings.root_path.is_none() {
+        // settings written by older cnblog
+        if let Some(rootpath) = args.rootpath.as_ref() {
+            settings.pin_root_path(rootpath);
+            settings.save(base_path);
+            println!("Info: root path is pinned to {}", settings.root_path.as_ref().unwrap());
+        }
+    }
+    let blog_root_path = match settings.check_root_path(args.rootpath.as_deref()) {
+        Ok(root_path) => root_path,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            exit(1);
+        }
+    };
+    let blog_root_path_str = blog_root_path.as_str();
+
+    // trash and diff need root path only
+    match &args.command {
+        Some(Command::Diff { paths }) => {
+            diff_blogs(&mut cfg, blog_root_path_str, paths);
+            return;
+        }
+        Some(Command::Trash { command: TrashCommand::List }) => {
+            list_trash(blog_root_path_str);
+            return;
+        }
+        Some(Command::Trash { command: TrashCommand::Purge { older_than } }) => {
+            purge_trash(blog_root_path_str, older_than);
+            return;
+        }
+        _ => {}
+    }
+
+    // must call it
+    match init_conn(&mut cfg) {
+        Ok(()) => {}
+        // password or app key is changed, log in again
+        Err(e @ BlobError::Auth(_)) if stdin().is_terminal() => {
+            eprintln!("Warning: {e}");
+            password = match login(base_path, &settings, &mut user_info, Credentials::default(), None) {
+                Ok(password) => password,
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    exit(1);
+                }
+            };
+            cfg.set_account(&user_info.username, &password, &user_info.app_key);
+            if let Err(e) = init_conn(&mut cfg) {
+                eprintln!("Error: {e}");
+                exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: {e}");
+            exit(1);
+        }
+    }
+    let mut weblog = new_weblog(&user_info.username, &password, &user_info.blogid, &user_info.app_key, &settings);
 
     // check blogs update
-    if cfg.check_blogs_info_update() {
-        // todo!("download remote new blog;");
-        download_remote_new_blog(&cfg, &mut weblog, blog_root_path_str);
-        //todo!("update remote changed blog");
-        update_remote_changed_blog(&cfg, &mut weblog, blog_root_path_str);
-        //todo!("move remote deleted blog;");
-        delete_remote_changed_blog(&cfg, &mut weblog, blog_root_path_str);
-        //todo!("overwrite local blogs database");
-        cfg = overwrite_local_blogs_database(cfg);
+    cfg = pull_remote_changes(cfg, &mut weblog, blog_root_path_str);
+
+    match args.command {
+        Some(Command::Import { all }) => {
+            let result = import::import_remote_posts(&cfg, &weblog, blog_root_path_str, all);
+            cfg.update_remote_database();
+            if let Err(e) = result {
+                eprintln!("Error: {e}");
+                exit(1);
+            }
+            return;
+        }
+        Some(Command::Verify { pull, overwrite, recreate }) => {
+            let changed = if pull {
+                Some(verify::Resolution::Pull)
+            } else if overwrite {
+                Some(verify::Resolution::Overwrite)
+            } else {
+                None
+            };
+            let missing = if recreate {
+                Some(verify::Resolution::Recreate)
+            } else if pull {
+                Some(verify::Resolution::Pull)
+            } else {
+                None
+            };
+            let result = verify::verify_remote_posts(&cfg, &weblog, blog_root_path_str, settings.publish, changed, missing);
+            // blogs resolved before aborting are kept
+            cfg.update_remote_database();
+            if let Err(e) = result {
+                eprintln!("Error: {e}");
+                exit(1);
+            }
+            return;
+        }
+        Some(Command::Encrypt { disable }) => {
+            if disable {
+                cfg.set_passphrase(None);
+                println!("Info: blogs info will be uploaded without encryption.");
+                if env::var(PASSPHRASE_ENV).is_ok() {
+                    println!("Warning: unset {PASSPHRASE_ENV}, otherwise blogs info will be encrypted again.");
+                }
+            } else {
+                if !stdin().is_terminal() {
+                    eprintln!("Error: a new passphrase must be typed twice in a terminal.");
+                    exit(1);
+                }
+                cfg.set_passphrase(Some(ask_passphrase(true)));
+                println!("Info: blogs info will be encrypted, every device needs this passphrase.");
+            }
+            cfg.update_remote_database();
+            return;
+        }
+        Some(Command::Trash { command: TrashCommand::Restore { ids } }) => {
+            let mut trash = Trash::open(blog_root_path_str);
+            for id in ids {
+                let entry = match trash.restore(blog_root_path_str, id) {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        eprintln!("Warning: can't restore {id}. Error: {e}");
+                        continue;
+                    }
+                };
+                let path = Path::new(blog_root_path_str).join(entry.blog_path.as_str());
+                // the draft of an unpublished blog is still on cnblog
+                if cfg.get_local_deleted_state(entry.postid).as_deref() == Some(DeletePolicy::Unpublish.state()) {
+                    match republish_blog(path.as_path(), &cfg, &weblog, entry.blog_path.as_str(), entry.postid) {
+                        Ok(()) => {
+                            println!("Restored: {}", entry.blog_path);
+                            continue;
+                        }
+                        Err(e) => eprintln!(
+                            "Warning: can't publish the draft of {}, upload it as a new blog. Error: {}",
+                            entry.blog_path, e
+                        ),
+                    }
+                }
+                upload_new_blog(path.as_path(), &cfg, &mut weblog, entry.blog_path.as_str(), settings.publish);
+                println!("Restored: {}", entry.blog_path);
+            }
+            cfg.update_remote_database();
+            return;
+        }
+        Some(Command::Watch { debounce, interval }) => {
+            // 1. push changes made before watching
+            let passphrase = cfg.passphrase().map(|passphrase| passphrase.to_string());
+            let result = sync_local_blogs_and_info(&cfg, &mut weblog, blog_root_path_str, &settings, args.allow_mass_delete);
+            cfg.update_remote_database();
+            if let Err(e) = result {
+                eprintln!("Error: {e}");
+            }
+
+            // 2. sync every change, blogs info is downloaded again for every sync
+            let sync = || {
+                let mut cfg = new_config(base_path_str, &user_info, &password, &settings, passphrase.clone());
+                if let Err(e) = cfg.init_conn() {
+                    eprintln!("Warning: can't download blogs info, try it later. Error: {e}");
+                    return;
+                }
+                let cfg = pull_remote_changes(cfg, &mut weblog, blog_root_path_str);
+                // blogs info is uploaded only if blogs are changed
+                let result = sync_local_blogs_and_info(&cfg, &mut weblog, blog_root_path_str, &settings, args.allow_mass_delete);
+                if !matches!(result, Ok(0)) {
+                    cfg.update_remote_database();
+                }
+                if let Err(e) = result {
+                    eprintln!("Error: {e}");
+                }
+            };
+            if let Err(e) = watch::watch(
+                blog_root_path_str,
+                &settings,
+                Duration::from_secs(debounce),
+                Duration::from_secs(interval),
+                sync,
+            ) {
+                eprintln!("Error: {e}");
+                exit(1);
+            }
+            return;
+        }
+        Some(Command::RestoreDb { .. })
+        | Some(Command::Diff { .. })
+        | Some(Command::Trash { .. })
+        | Some(Command::Profile { .. })
+        | Some(Command::Init { .. })
+        | Some(Command::Login { .. })
+        | Some(Command::Logout) => unreachable!(),
+        None => {}
     }
+
     // update local blog after finishing syncing local and remote database(blogs info)
     // todo!("upload new blog")
     //todo!("update local changed blog and upload");
     //todo!("update categories");
     //todo!("update(save) local blogs info and upload;");
-    sync_local_blogs_and_info(&cfg, &mut weblog, blog_root_path_str);
+    let result = sync_local_blogs_and_info(&cfg, &mut weblog, blog_root_path_str, &settings, args.allow_mass_delete);
     //todo!("sync local database and local blogs"); ??????
     //todo!("upload local database");
     cfg.update_remote_database();
+    if let Err(e) = result {
+        eprintln!("Error: {e}");
+        exit(1);
+    }
+}
+
+/// create config of user and settings
+fn new_config(base_path: &str, user_info: &UserInfo, password: &str, settings: &Settings, passphrase: Option<String>) -> Config {
+    let mut cfg = Config::new(
+        &user_info.username,
+        password,
+        &user_info.app_key,
+        user_info.postid,
+        &user_info.blogid,
+        base_path,
+    );
+    cfg.set_passphrase(passphrase);
+    if let Some(endpoint) = settings.endpoint.as_ref() {
+        cfg.set_endpoint(endpoint);
+    }
+    cfg.set_timeouts(Duration::from_secs(settings.connect_timeout), Duration::from_secs(settings.read_timeout));
+    cfg
+}
+
+/// create weblog of account with endpoint and timeouts of settings
+fn new_weblog(username: &str, password: &str, blogid: &str, app_key: &str, settings: &Settings) -> MetaWeblog {
+    let mut weblog = MetaWeblog::new(
+        username.to_string(),
+        password.to_string(),
+        blogid.to_string(),
+        app_key.to_string(),
+    );
+    if let Some(endpoint) = settings.endpoint.as_ref() {
+        weblog.set_endpoint(endpoint);
+    }
+    weblog.set_timeouts(Duration::from_secs(settings.connect_timeout), Duration::from_secs(settings.read_timeout));
+    weblog
+}
+
+/// download, update and delete blogs changed by other devices
+fn pull_remote_changes(cfg: Config, weblog: &mut MetaWeblog, root_path: &str) -> Config {
+    if !cfg.check_blogs_info_update() {
+        return cfg;
+    }
+    // todo!("download remote new blog;");
+    download_remote_new_blog(&cfg, weblog, root_path);
+    //todo!("update remote changed blog");
+    update_remote_changed_blog(&cfg, weblog, root_path);
+    //todo!("move remote deleted blog;");
+    delete_remote_changed_blog(&cfg, root_path);
+    //todo!("overwrite local blogs database");
+    overwrite_local_blogs_database(cfg)
+}
+
+/// init conn of config, ask passphrase while remote blogs info is encrypted
+/// without a terminal, `BlobError::NeedPassphrase` is returned
+fn init_conn(cfg: &mut Config) -> Result<(), BlobError> {
+    match cfg.init_conn() {
+        Err(BlobError::NeedPassphrase) if stdin().is_terminal() => {
+            cfg.set_passphrase(Some(ask_passphrase(false)));
+            cfg.init_conn()
+        }
+        result => result,
+    }
+}
+
+/// list, add or remove profiles
+fn profile_command(config_path: &Path, command: ProfileCommand) {
+    match command {
+        ProfileCommand::List => {
+            for name in profile::list(config_path) {
+                let base_path = profile::profile_path(config_path, name.as_str());
+                let user_info = Config::read_user_info_cfg(&base_path.join(USER_INFO_CFG)).unwrap();
+                let rootpath = Settings::load(base_path.as_path())
+                    .ok()
+                    .and_then(|settings| settings.root_path)
+                    .unwrap_or_else(|| "-".to_string());
+                println!("{:<16} {:<24} {}", name, user_info.username, rootpath);
+            }
+        }
+        ProfileCommand::Add { name, rootpath } => {
+            if let Err(e) = profile::check_name(name.as_str()) {
+                eprintln!("Error: {e}");
+                exit(1);
+            }
+            if profile::exists(config_path, name.as_str()) {
+                eprintln!("Error: profile `{name}` already exists.");
+                exit(1);
+            }
+            let base_path = profile::profile_path(config_path, name.as_str());
+            if let Err(e) = init_user_cfg(base_path.to_str().unwrap(), rootpath.as_deref(), Credentials::default(), None) {
+                eprintln!("{e}");
+                exit(1);
+            }
+            println!("Info: profile `{name}` is added, use it by `cnblog --profile {name}`.");
+        }
+        ProfileCommand::Remove { name } => {
+            if !profile::exists(config_path, name.as_str()) {
+                eprintln!("Error: profile `{name}` doesn't exist.");
+                exit(1);
+            }
+            // password saved by credential helper is outside of profile
+            let base_path = profile::profile_path(config_path, name.as_str());
+            if let (Some(user_info), Ok(settings)) = (
+                Config::read_user_info_cfg(&base_path.join(USER_INFO_CFG)),
+                Settings::load(base_path.as_path()),
+            ) {
+                let store = SecretStore::new(base_path.as_path(), settings.credential_helper.as_deref());
+                if let Err(e) = store.erase(user_info.username.as_str()) {
+                    eprintln!("Warning: can't wipe saved password. {e}");
+                }
+            }
+            if let Err(e) = profile::remove(config_path, name.as_str()) {
+                eprintln!("Error: can't remove profile `{name}`. {e}");
+                exit(1);
+            }
+            println!("Info: profile `{name}` is removed, its posts on cnblog are kept.");
+        }
+    }
+}
+
+/// get password of account
+/// password in user info written by older cnblog is moved to secret store if user agrees
+fn load_password(base_path: &Path, settings: &Settings, user_info: &UserInfo) -> Result<String, Box<dyn Error>> {
+    let store = SecretStore::new(base_path, settings.credential_helper.as_deref());
+
+    // 1. plaintext password
+    if !user_info.password.is_empty() {
+        let user_info_path = base_path.join(USER_INFO_CFG);
+        if stdin().is_terminal() && ask_yes(format!("Password is saved in plaintext in {:?}, move it to {}?", user_info_path, store).as_str()) {
+            store.store(user_info.username.as_str(), user_info.password.as_str())?;
+            Config::save_user_info_cfg(user_info, user_info_path.as_path());
+            println!("Info: password is removed from {:?}.", user_info_path);
+        } else {
+            eprintln!("Warning: password is saved in plaintext in {:?}.", user_info_path);
+        }
+        return Ok(user_info.password.clone());
+    }
+
+    // 2. saved password
+    if let Some(password) = store.get(user_info.username.as_str())? {
+        return Ok(password);
+    }
+
+    // 3. password isn't saved (logout), use environment variable or ask it
+    if let Ok(password) = env::var(credentials::PASSWORD_ENV) {
+        return Ok(password);
+    }
+    if !stdin().is_terminal() {
+        return Err(format!("password isn't saved and stdin isn't a terminal, set {}", credentials::PASSWORD_ENV).into());
+    }
+    let password = rpassword::prompt_password(format!("Please input password of {}: ", user_info.username))?;
+    Config::check_account(&new_weblog(&user_info.username, &password, &user_info.blogid, &user_info.app_key, settings))?;
+    store.store(user_info.username.as_str(), password.as_str())?;
+    Ok(password)
+}
+
+/// validate new credentials and save them, postid and blogid of user info are kept
+/// username and app key of user info are used while they're not given
+fn login(
+    base_path: &Path,
+    settings: &Settings,
+    user_info: &mut UserInfo,
+    credentials: Credentials,
+    credentials_file: Option<&str>,
+) -> Result<String, Box<dyn Error>> {
+    // 1. collect and check credentials
+    let mut credentials = credentials.or(Credentials::from_env());
+    if let Some(credentials_file) = credentials_file {
+        credentials = credentials.or(Credentials::from_file(Path::new(credentials_file))?);
+    }
+    let credentials = credentials.or(Credentials {
+        username: Some(user_info.username.clone()),
+        password: None,
+        app_key: Some(user_info.app_key.clone()),
+    });
+    let (username, password, app_key) = collect_credentials(credentials, None)?;
+    Config::check_account(&new_weblog(&username, &password, &user_info.blogid, &app_key, settings))?;
+
+    // 2. save password and user info
+    let store = SecretStore::new(base_path, settings.credential_helper.as_deref());
+    if username != user_info.username {
+        store.erase(user_info.username.as_str())?;
+    }
+    store.store(username.as_str(), password.as_str())?;
+    user_info.username = username;
+    user_info.app_key = app_key;
+    user_info.password.clear();
+    Config::save_user_info_cfg(user_info, base_path.join(USER_INFO_CFG).as_path());
+    Ok(password)
+}
+
+/// wipe saved password, and plaintext password written by older cnblog
+fn logout(base_path: &Path, settings: &Settings, mut user_info: UserInfo) {
+    let store = SecretStore::new(base_path, settings.credential_helper.as_deref());
+    if let Err(e) = store.erase(user_info.username.as_str()) {
+        eprintln!("Error: can't wipe saved password. {e}");
+        exit(1);
+    }
+    if !user_info.password.is_empty() {
+        user_info.password.clear();
+        Config::save_user_info_cfg(&user_info, base_path.join(USER_INFO_CFG).as_path());
+    }
+    println!("Info: password of {} is wiped.", user_info.username);
+}
+
+/// ask a yes/no question, `Enter` means yes
+fn ask_yes(question: &str) -> bool {
+    let mut buf = String::new();
+    print!("{} [Y/n] ", question);
+    stdout().flush().unwrap();
+    stdin().read_line(&mut buf).unwrap();
+    matches!(buf.trim(), "" | "y" | "Y" | "yes")
+}
+
+/// list blogs in trash
+fn list_trash(root_path: &str) {
+    let trash = Trash::open(root_path);
+    if trash.entries().is_empty() {
+        println!("Info: trash is empty.");
+    }
+    for entry in trash.entries() {
+        // a broken manifest may have a timestamp out of range
+        let deleted_at = match Local.timestamp_opt(entry.deleted_at, 0).single() {
+            Some(deleted_at) => deleted_at.format("%Y-%m-%d %H:%M").to_string(),
+            None => entry.deleted_at.to_string(),
+        };
+        println!(
+            "{:>4}. {} (postid: {}, deleted at {} on {})",
+            entry.id, entry.blog_path, entry.postid, deleted_at, entry.device
+        );
+    }
+}
+
+/// delete blogs moved to trash before the age
+fn purge_trash(root_path: &str, older_than: &str) {
+    let age = match trash::parse_duration(older_than) {
+        Some(age) => age,
+        None => {
+            eprintln!("Error: can't understand `{older_than}`, use a positive age like `30d`, `12h` or `2w`.");
+            exit(1);
+        }
+    };
+    let mut trash = Trash::open(root_path);
+    match trash.purge(Local::now().timestamp() - age) {
+        Ok(purged) => {
+            for entry in purged.iter() {
+                println!("Purged: {}", entry.blog_path);
+            }
+            println!("Info: {} blogs are purged.", purged.len());
+        }
+        Err(e) => {
+            eprintln!("Error: can't purge trash. {e}");
+            exit(1);
+        }
+    }
+}
+
+/// print changes of local blogs against the last synced version in cache
+/// `paths` limits blogs to these blogs or directories
+fn diff_blogs(cfg: &mut Config, root_path: &str, paths: &[String]) {
+    // remote blogs info isn't downloaded
+    if let Err(e) = cfg.open_local_conn() {
+        eprintln!("Error: {e}");
+        exit(1);
+    }
+    let paths: Vec<String> = paths
+        .iter()
+        .map(|path| path.replace('\\', "/").trim_end_matches('/').to_string())
+        .collect();
+
+    let mut changed = 0;
+    for blog_info in cfg.get_local_existed_blogs() {
+        // 1. filter blogs by paths
+        let blog_path = blog_info.blog_path.as_str();
+        if !paths.is_empty()
+            && !paths.iter().any(|path| blog_path == path || blog_path.starts_with(format!("{path}/").as_str()))
+        {
+            continue;
+        }
+
+        // 2. read local blog and its last synced version
+        let local = match fs::read_to_string(Path::new(root_path).join(blog_path)) {
+            Ok(local) => local,
+            Err(_) => {
+                println!("Deleted: {}", blog_path);
+                changed += 1;
+                continue;
+            }
+        };
+        let hash = match blog_info.hash.as_deref() {
+            Some(hash) if Utility::content_hash(local.as_str()) == hash => continue,
+            hash => hash,
+        };
+        let base = match hash.and_then(|hash| cfg.cached_post(blog_info.postid, hash)) {
+            Some(base) => base,
+            None => {
+                eprintln!("Warning: last synced version of {} isn't cached, sync it to diff it later.", blog_path);
+                continue;
+            }
+        };
+
+        // 3. print diff
+        let patch = diffy::DiffOptions::new()
+            .set_original_filename(format!("a/{blog_path}"))
+            .set_modified_filename(format!("b/{blog_path}"))
+            .create_patch(base.as_str(), local.as_str());
+        print!("{patch}");
+        changed += 1;
+    }
+    println!("Info: {} blogs are changed since last sync.", changed);
+}
+
+/// list backups of local blogs info or restore one of them
+fn restore_database(mut cfg: Config, backup: Option<String>, upload: bool) {
+    let database = cfg.blogs_info_cfg_path().to_path_buf();
+    let backups = backup::list(database.as_path());
+
+    // 1. list backups
+    let backup = match backup {
+        Some(backup) => backup,
+        None => {
+            if backups.is_empty() {
+                println!("Info: no backup of blogs info.");
+            }
+            for (i, path) in backups.iter().enumerate() {
+                println!("{:>3}. {}", i + 1, path.display());
+            }
+            return;
+        }
+    };
+
+    // 2. restore
+    let backup_path = match backup.parse::<usize>() {
+        Ok(i) if i >= 1 && i <= backups.len() => backups[i - 1].clone(),
+        _ => Path::new(backup.as_str()).to_path_buf(),
+    };
+    if let Err(e) = backup::restore(database.as_path(), backup_path.as_path()) {
+        eprintln!("Error: can't restore blogs info. {e}");
+        exit(1);
+    }
+    println!("Info: blogs info is restored from {}", backup_path.display());
+
+    // 3. upload
+    if upload {
+        // remote blogs info may be broken, so it isn't downloaded
+        if let Err(e) = cfg.open_local_conn() {
+            eprintln!("Error: {e}");
+            exit(1);
+        }
+        cfg.load_chunk_postids();
+        cfg.update_remote_database();
+    }
 }
 
 /// sync local blogs and local blogs info(database)
-fn sync_local_blogs_and_info(cfg: &Config, weblog: &mut MetaWeblog, root_path: &str) {
+/// deleting too many remote blogs at once is refused unless `allow_mass_delete`
+/// return the number of uploaded and deleted blogs
+fn sync_local_blogs_and_info(
+    cfg: &Config,
+    weblog: &mut MetaWeblog,
+    root_path: &str,
+    settings: &Settings,
+    allow_mass_delete: bool,
+) -> Result<usize, String> {
     // 1. get local database blogs path
     let blogs_path = cfg.get_local_existed_blogs_path();
     let blogs_path: BTreeMap<String, ()> = blogs_path
@@ -99,25 +854,29 @@ fn sync_local_blogs_and_info(cfg: &Config, weblog: &mut MetaWeblog, root_path: &
     // 2. walk through a directory
     let blogs_info = cfg.get_local_existed_blogs_info();
     let mut fs_blogs_path = HashSet::new();
-    for entry in WalkDir::new(root_path).into_iter().filter_entry(|e| is_not_hidden_and_is_markdown(e)){
+    let mut changed_blogs = Vec::new();
+    let mut synced = 0;
+    let is_synced = |entry: &DirEntry| {
+        is_not_hidden_and_is_markdown(entry) && !settings.is_ignored(blog_path_of(entry, root_path).as_str())
+    };
+    for entry in WalkDir::new(root_path).into_iter().filter_entry(is_synced) {
         let entry = entry.unwrap();
         if entry.path().is_dir() {
             continue;
         }
         let local_path = entry.path().strip_prefix(root_path).unwrap().as_os_str().to_str().unwrap();
-        let tlocal_path;
-        if cfg!(target_family="windows") {
-            tlocal_path = local_path.replace("\\", "/");
+        let tlocal_path = if cfg!(target_family="windows") {
+            local_path.replace('\\', "/")
         } else {
-            tlocal_path = local_path.to_string();
-        }
-        drop(local_path);
+            local_path.to_string()
+        };
         fs_blogs_path.insert(tlocal_path.clone());
 
         // 2.1 upload new blog
         if !blogs_path.contains_key(tlocal_path.as_str()) {
             println!("Will upload new blog: {}", tlocal_path.as_str());
-            upload_new_blog(&entry, &cfg, weblog, tlocal_path.as_str());
+            upload_new_blog(entry.path(), cfg, weblog, tlocal_path.as_str(), settings.publish);
+            synced += 1;
             continue;
         }
 
@@ -127,69 +886,156 @@ fn sync_local_blogs_and_info(cfg: &Config, weblog: &mut MetaWeblog, root_path: &
             let new_timestamp = Utility::get_file_timestamp(entry.path());
             if new_timestamp > *old_timestamp{
                 println!("Will upload changed blog: {}", tlocal_path.as_str());
-                update_local_blog(&entry, cfg, weblog, new_timestamp, *postid);
+                changed_blogs.push((entry, new_timestamp, *postid));
+                synced += 1;
             }
         }
     }
+    // 2.3 upload changed blogs in batches
+    update_local_blogs(changed_blogs, cfg, weblog, settings.publish);
+    cfg.prune_cache();
 
-    // 3. find deleted blogs and delete it
-    for blog_path in blogs_info.keys() {
-        if !fs_blogs_path.contains(blog_path) {
-            if let Some((_, postid)) = blogs_info.get(blog_path) {
-                let tblog_path;
-                if cfg!(target_family="windows") {
-                    tblog_path = blog_path.replace("/", "\\");
-                } else {
-                    tblog_path = blog_path.clone();
-                }
-                println!("Will delete(move) blog: {}", tblog_path.as_str());
-                delete_blog(tblog_path.as_str(), cfg, weblog, *postid);
-            }
+    // 3. find deleted blogs
+    // ignored blogs are not walked, but they're not deleted
+    let deleted_blogs: Vec<(&String, i32)> = blogs_info
+        .iter()
+        .filter(|(blog_path, _)| !fs_blogs_path.contains(*blog_path) && !settings.is_ignored(blog_path))
+        .map(|(blog_path, (_, postid))| (blog_path, *postid))
+        .collect();
+    if deleted_blogs.is_empty() {
+        return Ok(synced);
+    }
+
+    // 4. make sure deleting them is wanted
+    if !allow_mass_delete {
+        settings.check_mass_delete(deleted_blogs.len(), blogs_info.len())?;
+    }
+    if stdin().is_terminal() {
+        println!("The following remote blogs will be deleted:");
+        for (blog_path, _) in deleted_blogs.iter() {
+            println!("file: {}", blog_path);
+        }
+        if !ask_yes(format!("Delete {} remote blogs?", deleted_blogs.len()).as_str()) {
+            println!("Info: deleting is skipped, they will be asked again next time.");
+            return Ok(synced);
         }
     }
+
+    // 5. delete
+    for (blog_path, postid) in deleted_blogs {
+        let tblog_path = if cfg!(target_family="windows") {
+            blog_path.replace('/', "\\")
+        } else {
+            blog_path.clone()
+        };
+        println!("Will delete(move) blog: {}", tblog_path.as_str());
+        delete_blog(tblog_path.as_str(), cfg, weblog, postid, root_path, settings.delete_policy);
+        synced += 1;
+    }
+    Ok(synced)
 }
 
 /// Delete blog by postid and save info to database
-fn delete_blog(blog_path: &str, cfg: &Config, weblog: &mut MetaWeblog, postid: i32) {
-    // 1. delete remote blog
-    println!("Warning: delete remote blog {}", blog_path);
-    weblog.delete_post(postid.to_string().as_str(), true).unwrap();
+fn delete_blog(blog_path: &str, cfg: &Config, weblog: &mut MetaWeblog, postid: i32, root_path: &str, policy: DeletePolicy) {
+    // 1. remove remote blog by policy
+    let result = match policy {
+        DeletePolicy::Delete => {
+            println!("Warning: delete remote blog {}", blog_path);
+            weblog.delete_post(postid.to_string().as_str(), true).map(|_| ())
+        }
+        DeletePolicy::Unpublish => {
+            println!("Info: unpublish remote blog {}, it's kept as a draft", blog_path);
+            weblog
+                .get_post(postid.to_string().as_str())
+                .and_then(|post| weblog.edit_post(postid.to_string().as_str(), post, false))
+                .map(|_| ())
+        }
+        DeletePolicy::Archive => {
+            println!("Warning: archive remote blog {} into {}, then delete it", blog_path, trash::TRASH_DIR);
+            match weblog.get_post(postid.to_string().as_str()) {
+                Ok(post) => {
+                    // remote post is the only copy, it's kept if it can't be archived
+                    let trash_path = blog_path.replace('\\', "/");
+                    if let Err(e) = Trash::open(root_path).archive(trash_path.as_str(), postid, post.description.as_str()) {
+                        eprintln!("Warning: can't archive remote blog {}, it's retried by next sync. Error: {}", blog_path, e);
+                        return;
+                    }
+                    weblog.delete_post(postid.to_string().as_str(), true).map(|_| ())
+                }
+                Err(e) => Err(e),
+            }
+        }
+    };
 
     // 2. save database
-    cfg.delete_post(postid);
+    // a post removed on cnblog website can't be got, its state is still recorded
+    match result {
+        Ok(_) => {}
+        Err(e) if cfg.is_post_missing(&e) => {
+            eprintln!("Warning: remote blog {} is already removed on cnblog website", blog_path);
+        }
+        Err(e) => {
+            eprintln!(
+                "Warning: can't {} remote blog {}, it's retried by next sync. Error: {}",
+                policy.action(), blog_path, e
+            );
+            return;
+        }
+    }
+    cfg.delete_post(postid, policy.state());
 }
 
-/// update changed local blog
-fn update_local_blog(entry: &DirEntry, cfg: &Config, weblog: &mut MetaWeblog, timestamp:i64, postid: i32) {
-    // 1. generate basic post
-    let content = fs::read_to_string(entry.path()).unwrap();
-    let category = entry.path().parent().unwrap().file_name().unwrap().to_str().unwrap().to_string();
-    let mut post = Post::default();
-    post.description = content;
-    post.categories.push(category);
-    post.title = entry.path().file_name().unwrap().to_str().unwrap().to_string();
-
-    // 2. upload changed category
-    weblog.edit_post(postid.to_string().as_str(), post, true).unwrap();
-    // 3. update database
-    cfg.edit_post(postid, timestamp);
+/// update changed local blogs of (entry, timestamp, postid)
+fn update_local_blogs(blogs: Vec<(DirEntry, i64, i32)>, cfg: &Config, weblog: &mut MetaWeblog, publish: bool) {
+    // 1. generate basic posts
+    let mut contents = Vec::with_capacity(blogs.len());
+    let mut posts = Vec::with_capacity(blogs.len());
+    for (entry, _, postid) in blogs.iter() {
+        let content = fs::read_to_string(entry.path()).unwrap();
+        let category = entry.path().parent().unwrap().file_name().unwrap().to_str().unwrap().to_string();
+        let post = Post {
+            description: content.clone(),
+            categories: vec![category],
+            title: entry.path().file_name().unwrap().to_str().unwrap().to_string(),
+            ..Default::default()
+        };
+        posts.push((postid.to_string(), post));
+        contents.push(content);
+    }
+
+    // 2. upload changed blogs
+    let results = weblog.edit_posts(posts, publish);
+    for (((entry, timestamp, postid), content), result) in blogs.iter().zip(contents).zip(results) {
+        if let Err(e) = result {
+            eprintln!(
+                "Warning: can't upload {:?}, the post may be deleted on cnblog website (try `cnblog verify`). Error: {}",
+                entry.path(), e
+            );
+            continue;
+        }
+        // 3. update database and cache
+        cfg.edit_post(*postid, *timestamp, Utility::content_hash(content.as_str()).as_str());
+        cfg.cache_post(*postid, content.as_str());
+    }
 }
 
 /// upload local new blog and save info to local database
-fn upload_new_blog(entry: &DirEntry, cfg: &Config, weblog: &mut MetaWeblog, local_path: &str) {
+fn upload_new_blog(path: &Path, cfg: &Config, weblog: &mut MetaWeblog, local_path: &str, publish: bool) {
     // 1. generate basic post
-    let file_content = fs::read_to_string(entry.path()).unwrap();
-    let timestamp = Utility::get_file_timestamp(entry.path());
-    let category = entry.path().parent().unwrap().file_name().unwrap().to_str().unwrap().to_string();
+    let file_content = fs::read_to_string(path).unwrap();
+    let hash = Utility::content_hash(file_content.as_str());
+    let timestamp = Utility::get_file_timestamp(path);
+    let category = path.parent().unwrap().file_name().unwrap().to_str().unwrap().to_string();
 
-    let mut post = Post::default();
     let now = chrono::Local::now();
     let s = format!("{}-{:02}-{:02}T{:02}:{:02}:{:02}", now.year(), now.month(), now.day(), now.hour(), now.minute(), now.second());
-    post.dateCreated = iso8601::datetime(s.as_str()).unwrap();
-    post.description = file_content;
-    post.categories.push(category.clone());
-    post.categories.push("[Markdown]".to_string());
-    post.title = entry.path().file_name().unwrap().to_str().unwrap().to_string();
+    let post = Post {
+        date_created: iso8601::datetime(s.as_str()).unwrap(),
+        description: file_content.clone(),
+        categories: vec![category.clone(), "[Markdown]".to_string()],
+        title: path.file_name().unwrap().to_str().unwrap().to_string(),
+        ..Default::default()
+    };
 
     // 2. check category else upload new category
     let categories = cfg.get_local_categories();
@@ -197,15 +1043,50 @@ fn upload_new_blog(entry: &DirEntry, cfg: &Config, weblog: &mut MetaWeblog, loca
         println!("New category: {}", category);
         // insert new category and upload category
         cfg.new_category(&category);
-        let mut cate = WpCategory::default();
-        cate.name = category.clone();
+        let cate = WpCategory {
+            name: category.clone(),
+            ..Default::default()
+        };
         weblog.new_category(cate).unwrap();
         //categories.insert(category);
     }
 
     // 3. update database
-    let postid = weblog.new_post(post, true).unwrap();
-    cfg.new_post(local_path, postid.parse().unwrap(), timestamp);
+    let postid: i32 = weblog.new_post(post, publish).unwrap().parse().unwrap();
+    cfg.new_post(local_path, postid, timestamp, hash.as_str());
+    cfg.cache_post(postid, file_content.as_str());
+}
+
+/// publish the draft of an unpublished blog with local content and record it again
+fn republish_blog(path: &Path, cfg: &Config, weblog: &MetaWeblog, local_path: &str, postid: i32) -> Result<(), RpcError> {
+    // 1. generate basic post
+    let content = fs::read_to_string(path).unwrap();
+    let category = path.parent().unwrap().file_name().unwrap().to_str().unwrap().to_string();
+    let post = Post {
+        description: content.clone(),
+        categories: vec![category],
+        title: path.file_name().unwrap().to_str().unwrap().to_string(),
+        ..Default::default()
+    };
+
+    // 2. publish draft
+    weblog.edit_post(postid.to_string().as_str(), post, true)?;
+
+    // 3. update database, state is cleared
+    let hash = Utility::content_hash(content.as_str());
+    cfg.adopt_post(local_path, postid, Utility::get_file_timestamp(path), hash.as_str());
+    cfg.cache_post(postid, content.as_str());
+    Ok(())
+}
+
+/// get blog path (relative to root path, separated by `/`) of entry
+fn blog_path_of(entry: &DirEntry, root_path: &str) -> String {
+    let path = entry.path().strip_prefix(root_path).unwrap().to_str().unwrap();
+    if cfg!(target_family="windows") {
+        path.replace('\\', "/")
+    } else {
+        path.to_string()
+    }
 }
 
 /// if entry is not hidden and extension is markdown, return true, otherwise false;
@@ -228,10 +1109,7 @@ fn is_not_hidden_and_is_markdown(entry: &DirEntry) -> bool {
         Some(ext) =>  ext.to_str().unwrap_or(""),
         None => "",
     };
-    if suffix != "md" && suffix != "markdown" {
-        return false;
-    }
-    return true;
+    suffix == "md" || suffix == "markdown"
 }
 
 /// overwrite local blogs database
@@ -239,27 +1117,71 @@ fn overwrite_local_blogs_database(cfg: Config) -> Config {
     cfg.overwrite_local_database()
 }
 
+/// get content of blogs by blogs_info
+/// cached blogs are not downloaded again, the others are downloaded in batches and cached
+/// a blog which can't be downloaded is None, so one missing post doesn't stop the others
+fn fetch_blogs_by_blogs_info(blogs_info: &[BlogsInfoDO], cfg: &Config, weblog: &mut MetaWeblog) -> Vec<Option<String>> {
+    // 1. read cache by hash
+    let mut contents: Vec<Option<String>> = blogs_info
+        .iter()
+        .map(|blog_info| {
+            let hash = blog_info.hash.as_deref()?;
+            cfg.cached_post(blog_info.postid, hash)
+        })
+        .collect();
+    let missing: Vec<usize> = (0..contents.len()).filter(|i| contents[*i].is_none()).collect();
+    if missing.len() < contents.len() {
+        println!("Info: {} blogs are unchanged since last sync, read them from cache.", contents.len() - missing.len());
+    }
+
+    // 2. download the others in batches
+    let postids: Vec<String> = missing.iter().map(|i| blogs_info[*i].postid.to_string()).collect();
+    let blogs = weblog.get_posts(&postids);
+    // a post deleted on cnblog website only skips itself
+    for (i, blog) in missing.into_iter().zip(blogs) {
+        match blog {
+            Ok(blog) => {
+                cfg.cache_post(blogs_info[i].postid, blog.description.as_str());
+                contents[i] = Some(blog.description);
+            }
+            Err(e) => eprintln!(
+                "Warning: can't download {}, it's skipped (try `cnblog verify`). Error: {}",
+                blogs_info[i].blog_path, e
+            ),
+        }
+    }
+    contents
+}
+
+/// write content of blog and create its directory
+fn save_blog(blog_path: &Path, content: &str) {
+    let dir_path = blog_path.parent().unwrap();
+    if !dir_path.exists() {
+        fs::create_dir_all(dir_path).unwrap();
+    }
+    fs::write(blog_path, content).unwrap();
+}
+
 /// Save the corresponding blog according to the blogs_info
 /// and change the modified timestamp of the blog at the same time
 fn save_blogs_by_blogs_info(
     blogs_info: Vec<BlogsInfoDO>,
+    cfg: &Config,
     weblog: &mut MetaWeblog,
     root_path: &str,
 ) {
     let path = Path::new(root_path);
-    for blog_info in blogs_info {
-        // 1. download
-        let blog = weblog
-            .get_post(blog_info.postid.to_string().as_str())
-            .unwrap();
+    // 1. get content from cache or download it
+    let blogs = fetch_blogs_by_blogs_info(&blogs_info, cfg, weblog);
+    for (blog_info, blog) in blogs_info.into_iter().zip(blogs) {
+        let blog = match blog {
+            Some(blog) => blog,
+            None => continue,
+        };
 
         // 2. save blog
         let blog_path = path.join(blog_info.blog_path.as_str());
-        let dir_path = blog_path.parent().unwrap();
-        if !dir_path.exists() {
-            fs::create_dir_all(dir_path).unwrap();
-        }
-        fs::write(blog_path.as_path(), blog.description).unwrap();
+        save_blog(blog_path.as_path(), blog.as_str());
 
         // 3. change file mtime
         Utility::modify_file_timestamp(blog_path.as_path(), blog_info.timestamp);
@@ -279,80 +1201,133 @@ fn download_remote_new_blog(cfg: &Config, weblog: &mut MetaWeblog, root_path: &s
     for blog_info in blogs_info.iter() {
         println!("file: {}", blog_info.blog_path);
     }
-    save_blogs_by_blogs_info(blogs_info, weblog, root_path);
-}
-
-/// delete(move) file from root_path to delete_path
-fn delete_blogs_by_blogs_info(blogs_info: Vec<BlogsInfoDO>, root_path: &str, delete_path: &str) {
-    // 1. determine whether the delete path exists
-    let delete_path = Path::new(delete_path);
-    if !delete_path.exists() {
-        create_dir(delete_path).unwrap();
-    }
-    if !delete_path.is_dir() {
-        eprintln!(
-            "{:?} already exists but is not a dictory",
-            delete_path.to_str()
-        );
-        panic!(
-            "{:?} already exists but is not a dictory",
-            delete_path.to_str()
-        );
-    }
-
-    // 2. move file to delete path with postid name
-    // the new and old file need to be in same mount point
-    let root_path = Path::new(root_path);
-    for blog_info in blogs_info {
-        let old_path = root_path.join(blog_info.blog_path);
-        let new_path = delete_path
-            .join(blog_info.postid.to_string() + old_path.file_name().unwrap().to_str().unwrap());
-        if let Err(e) = fs::rename(old_path.as_path(), new_path) {
-            eprintln!(
-                "Warning: a error occurred while moving {:?} to {:?}. Error: {} ",
-                old_path, delete_path, e
-            );
-        }
-    }
+    save_blogs_by_blogs_info(blogs_info, cfg, weblog, root_path);
 }
 
 /// compare local and remote info to delete old blog
 /// Note: old blog will be moved to delete dir
-fn delete_remote_changed_blog(cfg: &Config, weblog: &mut MetaWeblog, root_path: &str) {
+fn delete_remote_changed_blog(cfg: &Config, root_path: &str) {
     // 1. get deleted blog by comparing remote and local database
     let blogs_info = cfg.get_remote_deleted_blogs_info();
 
     // 2. delete(move) blog
     let deleted_root_path = Path::new(root_path)
-        .join(".cnblog_deleted");
+        .join(trash::TRASH_DIR);
     println!(
         "Warning: the following file will be moved to {}.",
         deleted_root_path.to_str().unwrap()
     );
+    // remote post is already removed by the device recording its state
     for blog_info in blogs_info.iter() {
-        weblog.delete_post(blog_info.postid.to_string().as_str(), true).unwrap();
-        println!("file: {}", blog_info.blog_path);
+        println!("file: {} ({})", blog_info.blog_path, blog_info.state.as_deref().unwrap_or("deleted"));
     }
-    delete_blogs_by_blogs_info(blogs_info, root_path, deleted_root_path.to_str().unwrap());
+    trash::delete_blogs_by_blogs_info(blogs_info, root_path);
 }
 
 /// update changedblog by remote blog info
-/// Note: it will overwrite older local blog
+/// Note: local changes are merged by the cached last synced version,
+/// a local blog which can't be merged is moved to trash and overwritten
 fn update_remote_changed_blog(cfg: &Config, weblog: &mut MetaWeblog, root_path: &str) {
     // 1. get changed blogsinfo by comparing remote and local database
     let blogs_info = cfg.get_remote_changed_blogs_info();
+    let local_blogs: HashMap<i32, BlogsInfoDO> = cfg
+        .get_local_existed_blogs()
+        .into_iter()
+        .map(|blog_info| (blog_info.postid, blog_info))
+        .collect();
 
-    // 2. save changed blog
+    // 2. get remote content
     println!("Warning: the following file will be overwritten!");
     for blog_info in blogs_info.iter() {
         println!("file: {}", blog_info.blog_path);
     }
-    save_blogs_by_blogs_info(blogs_info, weblog, root_path);
+    let blogs = fetch_blogs_by_blogs_info(&blogs_info, cfg, weblog);
+
+    // 3. merge local changes and save changed blog
+    let mut trash = Trash::open(root_path);
+    for (blog_info, remote) in blogs_info.into_iter().zip(blogs) {
+        let remote = match remote {
+            Some(remote) => remote,
+            None => continue,
+        };
+        let blog_path = Path::new(root_path).join(blog_info.blog_path.as_str());
+        let merge = match (local_blogs.get(&blog_info.postid), fs::read_to_string(blog_path.as_path())) {
+            (Some(local_info), Ok(local)) if is_local_changed(local_info, blog_path.as_path(), local.as_str()) => {
+                let base = local_info
+                    .hash
+                    .as_deref()
+                    .and_then(|hash| cfg.cached_post(blog_info.postid, hash));
+                merge_blog(base.as_deref(), local, remote.as_str())
+            }
+            _ => Merge::Remote,
+        };
+        match merge {
+            Merge::Remote => {}
+            Merge::Merged(merged) => {
+                println!("Info: local changes of {} are merged, it will be uploaded.", blog_info.blog_path);
+                save_blog(blog_path.as_path(), merged.as_str());
+                // newer than remote blog, so it's uploaded by the next sync
+                let timestamp = Local::now().timestamp().max(blog_info.timestamp + 1);
+                Utility::modify_file_timestamp(blog_path.as_path(), timestamp);
+                continue;
+            }
+            Merge::Conflict(local) => {
+                eprintln!(
+                    "Warning: local changes of {} conflict with remote changes, local blog is moved to {}.",
+                    blog_info.blog_path, trash::TRASH_DIR
+                );
+                if let Err(e) = trash.archive(blog_info.blog_path.as_str(), blog_info.postid, local.as_str()) {
+                    eprintln!("Error: can't move {} to {}. Error: {}", blog_info.blog_path, trash::TRASH_DIR, e);
+                    exit(1);
+                }
+            }
+        }
+        save_blog(blog_path.as_path(), remote.as_str());
+        Utility::modify_file_timestamp(blog_path.as_path(), blog_info.timestamp);
+    }
+}
+
+/// result of merging local changes into a remote changed blog
+enum Merge {
+    /// local blog isn't changed, take remote blog
+    Remote,
+    /// both changes are kept
+    Merged(String),
+    /// changes overlap or there is no base, it holds local blog
+    Conflict(String),
+}
+
+/// whether local blog is changed after last sync
+fn is_local_changed(local_info: &BlogsInfoDO, blog_path: &Path, local: &str) -> bool {
+    match local_info.hash.as_deref() {
+        Some(hash) => Utility::content_hash(local) != hash,
+        // recorded by older cnblog
+        None => Utility::get_file_timestamp(blog_path) > local_info.timestamp,
+    }
+}
+
+/// three-way merge of changed local blog and remote blog, `base` is the last synced version
+fn merge_blog(base: Option<&str>, local: String, remote: &str) -> Merge {
+    if local == remote {
+        return Merge::Remote;
+    }
+    match base.map(|base| diffy::merge(base, local.as_str(), remote)) {
+        Some(Ok(merged)) if merged == remote => Merge::Remote,
+        Some(Ok(merged)) => Merge::Merged(merged),
+        _ => Merge::Conflict(local),
+    }
 }
 
 /// init user config
 /// After this function is excuted, it will ensure that the configuration file exsits.
-fn init_user_cfg(base_path: &str) -> Result<(), Error> {
+/// `rootpath` (or `articles`) is pinned as the root path of articles
+/// `credentials` and `credentials_file` are used before asking user
+fn init_user_cfg(
+    base_path: &str,
+    rootpath: Option<&str>,
+    credentials: Credentials,
+    credentials_file: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
     // Make sure the dictory exsits
     let base_path = Path::new(base_path);
     if base_path.exists() {
@@ -372,24 +1347,26 @@ fn init_user_cfg(base_path: &str) -> Result<(), Error> {
         return Ok(());
     }
 
-    // When false, we need to ask the user for their account and password
-    let (username, password, app_key) = ask_question();
-    Config::check_account(username.as_str(), password.as_str(), app_key.as_str())?;
+    // When false, we need the account and password
+    let (username, password, app_key) = collect_credentials(credentials, credentials_file)?;
+    let mut settings = Settings::load(base_path)?;
+    // real blogid is unknown yet, a fake value is enough
+    let weblog = new_weblog(&username, &password, "123", &app_key, &settings);
+    Config::check_account(&weblog)?;
 
     // Check whether the master postid exists
-    let num = Config::try_get_master_postid(&username, &password, &app_key)?;
+    let num = Config::try_get_master_postid(&weblog)?;
     let blogs_path = base_path.join(BLOGS_INFO_CFG);
     let blogs_path = blogs_path.as_path();
-    let postid;
-    if num == 0 {
+    let postid = if num == 0 {
         // Not exists
         // Now we need to create a new blog info
         Config::init_blogs_cfg(blogs_path).unwrap();
-        postid = Config::upload_new_blogs_cfg(&username, &password, &app_key, blogs_path);
+        Config::upload_new_blogs_cfg(&weblog, blogs_path)
     } else {
         // Exists
         // Dowload BlogsInfo
-        let cfg = Config::new(
+        let mut cfg = Config::new(
             &username,
             &password,
             &app_key,
@@ -397,18 +1374,69 @@ fn init_user_cfg(base_path: &str) -> Result<(), Error> {
             "123",
             base_path.to_str().unwrap(),
         );
-        cfg.download_blogs_info();
+        if let Some(endpoint) = settings.endpoint.as_ref() {
+            cfg.set_endpoint(endpoint);
+        }
+        cfg.set_timeouts(Duration::from_secs(settings.connect_timeout), Duration::from_secs(settings.read_timeout));
+        cfg.set_passphrase(env::var(PASSPHRASE_ENV).ok());
+        match cfg.download_blogs_info() {
+            Err(BlobError::NeedPassphrase) if stdin().is_terminal() => {
+                cfg.set_passphrase(Some(ask_passphrase(false)));
+                cfg.download_blogs_info()?;
+            }
+            result => result?,
+        }
         cfg.force_increase_timestamp_to_download_blogs();
-        postid = num;
-    }
+        num
+    };
+
+    // Save user info and password
+    let store = SecretStore::new(base_path, settings.credential_helper.as_deref());
+    store.store(username.as_str(), password.as_str())?;
+    println!("Info: password is saved in {}.", store);
+    Config::write_user_info_cfg(&weblog, &username, &password, &app_key, postid, &user_path);
 
-    // Save user info
-    Config::write_user_info_cfg(&username, &password, &app_key, postid, &user_path);
+    // Pin root path
+    let rootpath = rootpath.unwrap_or("articles");
+    fs::create_dir_all(rootpath)?;
+    settings.pin_root_path(rootpath);
+    settings.save(base_path);
     Ok(())
 }
 
-/// ask username and password and app_key
-fn ask_question() -> (String, String, String) {
+/// get credentials from flags, environment variables and credentials file in turn
+/// missing ones are asked only while stdin is a terminal
+fn collect_credentials(
+    credentials: Credentials,
+    credentials_file: Option<&str>,
+) -> Result<(String, String, String), Box<dyn Error>> {
+    let mut credentials = credentials.or(Credentials::from_env());
+    if let Some(credentials_file) = credentials_file {
+        credentials = credentials.or(Credentials::from_file(Path::new(credentials_file))?);
+    }
+    let missing = credentials.missing();
+    if !missing.is_empty() {
+        if !stdin().is_terminal() {
+            return Err(format!(
+                "missing {} of cnblog account and stdin isn't a terminal, pass it by `cnblog init` flags, {}/{}/{} or `--credentials`",
+                missing.join(", "),
+                credentials::USERNAME_ENV,
+                credentials::PASSWORD_ENV,
+                credentials::APP_KEY_ENV
+            )
+            .into());
+        }
+        credentials = ask_question(credentials);
+    }
+    Ok((
+        credentials.username.unwrap(),
+        credentials.password.unwrap(),
+        credentials.app_key.unwrap(),
+    ))
+}
+
+/// ask missing username and password and app_key
+fn ask_question(credentials: Credentials) -> Credentials {
     // 1. print a prompt
     println!(
         "The user info config file was not founded!\
@@ -416,29 +1444,152 @@ fn ask_question() -> (String, String, String) {
             (Press `Enter` confirm)"
     );
 
-    let mut buf = String::new();
+    let ask = |prompt: &str| {
+        let mut buf = String::new();
+        print!("{}", prompt);
+        stdout().flush().unwrap();
+        stdin().read_line(&mut buf).unwrap();
+        buf.trim().to_string()
+    };
 
     // 2. get username
-    print!("Please input your username: ");
-    stdout().flush().unwrap();
-    stdin().read_line(&mut buf).unwrap();
-    let username = buf.trim().to_string();
+    let username = credentials.username.unwrap_or_else(|| ask("Please input your username: "));
 
     // 3. get password
-    print!("Please input your password: ");
-    stdout().flush().unwrap();
-    buf.clear();
-    stdin().read_line(&mut buf).unwrap();
-    let password = buf.trim().to_string();
+    let password = credentials
+        .password
+        .unwrap_or_else(|| rpassword::prompt_password("Please input your password: ").unwrap());
 
     // 4. get app_key
-    print!("Please input your app_key: ");
-    stdout().flush().unwrap();
-    buf.clear();
-    stdin().read_line(&mut buf).unwrap();
-    let app_key = buf.trim().to_string();
+    let app_key = credentials.app_key.unwrap_or_else(|| ask("Please input your app_key: "));
+
+    println!();
+
+    Credentials {
+        username: Some(username),
+        password: Some(password),
+        app_key: Some(app_key),
+    }
+}
 
-    println!("");
+/// ask passphrase of blogs info
+/// a new passphrase needs to be typed twice
+fn ask_passphrase(new: bool) -> String {
+    loop {
+        let passphrase = rpassword::prompt_password("Please input passphrase of blogs info: ").unwrap();
+        let passphrase = passphrase.trim().to_string();
+        if passphrase.is_empty() {
+            continue;
+        }
+        if !new {
+            return passphrase;
+        }
 
-    (username, password, app_key)
+        let again = rpassword::prompt_password("Please input passphrase again: ").unwrap();
+        if again.trim() == passphrase {
+            return passphrase;
+        }
+        eprintln!("Warning: passphrases don't match, please try again.");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::{delete_blog, merge_blog, save_blogs_by_blogs_info, Merge};
+    use crate::meta_weblog::cfg::{BlogsInfoDO, Config, BLOGS_INFO_CFG};
+    use crate::meta_weblog::rpc::mock::{fault, local_weblog, post_content, response, serve};
+    use crate::settings::DeletePolicy;
+
+    /// config with a local database, its requests go to `endpoint`
+    fn local_config(dir: &TempDir, endpoint: &str) -> Config {
+        Config::init_blogs_cfg(dir.path().join(BLOGS_INFO_CFG).as_path()).unwrap();
+        let mut cfg = Config::new("user", "password", "app", 0, "1", dir.path().to_str().unwrap());
+        cfg.set_endpoint(endpoint);
+        cfg.open_local_conn().unwrap();
+        cfg
+    }
+
+    #[test]
+    fn merge_local_and_remote_changes() {
+        let base = "# title\n\nfirst\n\nsecond\n";
+        let local = "# title\n\nfirst, local\n\nsecond\n".to_string();
+        let remote = "# title\n\nfirst\n\nsecond, remote\n";
+        match merge_blog(Some(base), local, remote) {
+            Merge::Merged(merged) => assert_eq!(merged, "# title\n\nfirst, local\n\nsecond, remote\n"),
+            _ => panic!("changes should be merged"),
+        }
+
+        // same change on both sides
+        assert!(matches!(merge_blog(Some(base), remote.to_string(), remote), Merge::Remote));
+    }
+
+    #[test]
+    fn conflict_keeps_local_blog() {
+        let base = "# title\n\nfirst\n";
+        let local = "# title\n\nlocal\n".to_string();
+        let remote = "# title\n\nremote\n";
+        assert!(matches!(merge_blog(Some(base), local.clone(), remote), Merge::Conflict(content) if content == local));
+        assert!(matches!(merge_blog(None, local.clone(), remote), Merge::Conflict(_)));
+    }
+
+    #[test]
+    fn delete_blog_records_removed_post() {
+        let (config_dir, root) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        // rejected account, missing post, deleted post
+        let responses = vec![
+            fault(),
+            fault(),
+            fault(),
+            response("<array><data></data></array>"),
+            response("<boolean>1</boolean>"),
+        ];
+        let (endpoint, server) = serve(responses);
+        let cfg = local_config(&config_dir, endpoint.as_str());
+        let mut weblog = local_weblog(endpoint.as_str());
+        for postid in 1..=3 {
+            cfg.new_post(format!("Rust/{postid}.md").as_str(), postid, 0, "");
+        }
+        let root_path = root.path().to_str().unwrap();
+        let postids = |cfg: &Config| cfg.get_local_existed_blogs().iter().map(|blog_info| blog_info.postid).collect::<Vec<_>>();
+
+        // it's retried by next sync
+        delete_blog("Rust/1.md", &cfg, &mut weblog, 1, root_path, DeletePolicy::Delete);
+        assert_eq!(postids(&cfg), [1, 2, 3]);
+        delete_blog("Rust/2.md", &cfg, &mut weblog, 2, root_path, DeletePolicy::Delete);
+        assert_eq!(postids(&cfg), [1, 3]);
+        delete_blog("Rust/3.md", &cfg, &mut weblog, 3, root_path, DeletePolicy::Delete);
+        assert_eq!(postids(&cfg), [1]);
+        assert_eq!(server.join().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn missing_post_skips_only_itself() {
+        let (config_dir, root) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let methods = "<array><data><value><string>metaWeblog.getPost</string></value></data></array>";
+        let responses = vec![response(methods), response(post_content("a.md", "remote").as_str()), fault()];
+        let (endpoint, server) = serve(responses);
+        let cfg = local_config(&config_dir, endpoint.as_str());
+        let mut weblog = local_weblog(endpoint.as_str());
+        let blogs_info = ["a.md", "b.md"]
+            .iter()
+            .zip(1..)
+            .map(|(name, postid)| BlogsInfoDO {
+                blog_path: format!("Rust/{name}"),
+                postid,
+                timestamp: 0,
+                deleted: false,
+                hash: None,
+                state: None,
+            })
+            .collect();
+
+        save_blogs_by_blogs_info(blogs_info, &cfg, &mut weblog, root.path().to_str().unwrap());
+        assert_eq!(fs::read_to_string(root.path().join("Rust/a.md")).unwrap(), "remote");
+        assert!(!root.path().join("Rust/b.md").exists());
+        server.join().unwrap();
+    }
 }
@@ -0,0 +1,131 @@
+// timestamped backups of local blogs info database
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use rusqlite::{Connection, OpenFlags};
+
+/// directory of backups in config directory
+pub const BACKUP_DIR: &str = "backups";
+/// number of backups to keep
+const MAX_BACKUPS: usize = 10;
+const BACKUP_PREFIX: &str = "blogs_info.";
+const BACKUP_SUFFIX: &str = ".sqlite";
+
+/// copy database into backup directory and remove the oldest backups
+pub fn backup(database: &Path) -> io::Result<PathBuf> {
+    // 1. find a free backup name
+    let backup_dir = backup_dir(database);
+    fs::create_dir_all(backup_dir.as_path())?;
+    let stamp = Local::now().format("%Y%m%d%H%M%S%3f").to_string();
+    let mut backup_path = backup_dir.join(format!("{}{}{}", BACKUP_PREFIX, stamp, BACKUP_SUFFIX));
+    let mut n = 1;
+    while backup_path.exists() {
+        // `_` sorts after `.`, so the order of names is still the order of backups
+        backup_path = backup_dir.join(format!("{}{}_{:03}{}", BACKUP_PREFIX, stamp, n, BACKUP_SUFFIX));
+        n += 1;
+    }
+
+    // 2. copy
+    fs::copy(database, backup_path.as_path())?;
+
+    // 3. rotate
+    for old in list(database).into_iter().skip(MAX_BACKUPS) {
+        fs::remove_file(old)?;
+    }
+    Ok(backup_path)
+}
+
+/// list backups of database, newest first
+pub fn list(database: &Path) -> Vec<PathBuf> {
+    let mut backups: Vec<PathBuf> = match fs::read_dir(backup_dir(database)) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                let name = path.file_name().unwrap().to_str().unwrap_or("");
+                name.starts_with(BACKUP_PREFIX) && name.ends_with(BACKUP_SUFFIX)
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    // timestamp in name keeps the order
+    backups.sort();
+    backups.reverse();
+    backups
+}
+
+/// check database by `PRAGMA integrity_check` and its tables
+pub fn validate(database: &Path) -> Result<(), String> {
+    let conn = Connection::open_with_flags(database, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| e.to_string())?;
+    let result: String = conn
+        .query_row("pragma integrity_check", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if result != "ok" {
+        return Err(result);
+    }
+    let tables: i32 = conn
+        .query_row(
+            "select count(*) from sqlite_master where type = 'table' and name in ('BlogsInfo', 'Category')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if tables != 2 {
+        return Err("BlogsInfo or Category table is missing".to_string());
+    }
+    Ok(())
+}
+
+/// replace database with a backup, current database is backed up at first
+pub fn restore(database: &Path, backup_path: &Path) -> Result<(), String> {
+    validate(backup_path).map_err(|e| format!("backup {:?} is broken: {}", backup_path, e))?;
+    if database.exists() {
+        backup(database).map_err(|e| e.to_string())?;
+    }
+    fs::copy(backup_path, database).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn backup_dir(database: &Path) -> PathBuf {
+    database.parent().unwrap().join(BACKUP_DIR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database(dir: &Path) -> PathBuf {
+        let path = dir.join("blogs_info.sqlite");
+        let conn = Connection::open(path.as_path()).unwrap();
+        conn.execute_batch("create table BlogsInfo (id integer); create table Category (id integer);")
+            .unwrap();
+        path
+    }
+
+    #[test]
+    fn rotate_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = database(dir.path());
+        for _ in 0..MAX_BACKUPS + 2 {
+            backup(database.as_path()).unwrap();
+        }
+        let backups = list(database.as_path());
+        assert_eq!(backups.len(), MAX_BACKUPS);
+        assert!(backups[0] > backups[1]);
+    }
+
+    #[test]
+    fn validate_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = database(dir.path());
+        assert!(validate(database.as_path()).is_ok());
+
+        let broken = dir.path().join("broken.sqlite");
+        fs::write(broken.as_path(), b"not a database").unwrap();
+        assert!(validate(broken.as_path()).is_err());
+        assert!(restore(database.as_path(), broken.as_path()).is_err());
+    }
+}
@@ -0,0 +1,301 @@
+// encode blogs info database to text saved in master post (and chunk posts)
+//
+// master post: `CNBLOG_MANIFEST:<base64 of manifest json>:<first chunk>`
+// chunk post:  `<next chunk>`
+// payload (all chunks joined) is base64 of the zlib compressed (and maybe encrypted) database
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::crypto::{self, CryptoError};
+use super::migration::{MigrationError, SCHEMA_VERSION};
+use super::rpc::RpcError;
+
+/// max length of payload saved in one post
+pub const CHUNK_SIZE: usize = 500_000;
+/// current manifest version
+const MANIFEST_VERSION: u32 = 1;
+const MANIFEST_PREFIX: &str = "CNBLOG_MANIFEST:";
+
+/// manifest saved in master post
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Manifest {
+    pub version: u32,
+    // compression of database, only "zlib" now
+    pub compression: String,
+    pub encrypted: bool,
+    // length of payload
+    pub length: usize,
+    // sha256 of payload
+    pub sha256: String,
+    // postids of chunk posts following master post
+    pub chunks: Vec<i32>,
+    // sha256 of database, missing in manifest written by older cnblog
+    // it's omitted while encrypted, manifest is cleartext and the hash would reveal the database
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub db_sha256: Option<String>,
+    // `PRAGMA user_version` of database
+    #[serde(default)]
+    pub schema_version: i32,
+}
+
+/// content of master post
+pub enum MasterPost {
+    /// manifest and first chunk of payload
+    Manifest(Manifest, String),
+    /// base64 database written by older cnblog
+    Legacy(String),
+}
+
+/// error while decoding blogs info from master post
+#[derive(Debug)]
+pub enum BlobError {
+    /// blogs info is encrypted but no passphrase is given
+    NeedPassphrase,
+    /// blogs info can't be decrypted
+    Crypto(CryptoError),
+    /// blogs info isn't valid base64
+    Decode(base64::DecodeError),
+    /// username, password or app key is rejected by cnblog
+    Auth(RpcError),
+    /// master post can't be downloaded
+    Master(RpcError),
+    /// manifest in master post is broken
+    Manifest(String),
+    /// chunk post can't be downloaded
+    Chunk(i32, RpcError),
+    /// joined chunks or database don't match manifest
+    Checksum,
+    /// downloaded database is broken
+    Invalid(String),
+    /// blogs info can't be decompressed
+    Decompress(io::Error),
+    /// remote blogs info was uploaded by a newer cnblog
+    NewerSchema(i32),
+    /// blogs info can't be migrated to current schema
+    Schema(MigrationError),
+}
+
+impl fmt::Display for BlobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlobError::NeedPassphrase => write!(
+                f,
+                "remote blogs info is encrypted, a passphrase is needed (set CNBLOG_PASSPHRASE)"
+            ),
+            BlobError::Crypto(e) => write!(f, "can't decrypt remote blogs info: {}", e),
+            BlobError::Decode(e) => write!(f, "remote blogs info is broken: {}", e),
+            BlobError::Auth(e) => write!(
+                f,
+                "cnblog rejects the account, the password or app key may be changed, log in again by `cnblog login`. Error: {}",
+                e
+            ),
+            BlobError::Master(e) => write!(f, "can't download remote blogs info: {}", e),
+            BlobError::Manifest(e) => write!(f, "manifest of remote blogs info is broken: {}", e),
+            BlobError::Chunk(postid, e) => {
+                write!(f, "can't download chunk post {} of remote blogs info: {}", postid, e)
+            }
+            BlobError::Checksum => write!(
+                f,
+                "remote blogs info doesn't match its checksum, some chunks may be missing or modified"
+            ),
+            BlobError::Decompress(e) => write!(f, "can't decompress remote blogs info: {}", e),
+            BlobError::Invalid(e) => write!(f, "remote blogs info is broken: {}", e),
+            BlobError::NewerSchema(version) => write!(
+                f,
+                "remote blogs info was uploaded by a newer cnblog (schema version {} > {}), please upgrade cnblog before syncing",
+                version, SCHEMA_VERSION
+            ),
+            BlobError::Schema(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for BlobError {}
+
+/// encode database to manifest and chunks of payload
+/// `chunks` of manifest is empty, it should be filled after chunk posts are uploaded
+pub fn encode(database: &[u8], passphrase: Option<&str>, schema_version: i32) -> (Manifest, Vec<String>) {
+    // 1. compress
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(database).unwrap();
+    let mut bytes = encoder.finish().unwrap();
+
+    // 2. encrypt
+    if let Some(passphrase) = passphrase {
+        bytes = crypto::encrypt(&bytes, passphrase);
+    }
+
+    // 3. base64 and split
+    let payload = base64::encode(bytes);
+    let manifest = Manifest {
+        version: MANIFEST_VERSION,
+        compression: "zlib".to_string(),
+        encrypted: passphrase.is_some(),
+        length: payload.len(),
+        sha256: format!("{:x}", Sha256::digest(payload.as_bytes())),
+        chunks: Vec::new(),
+        db_sha256: match passphrase {
+            // decryption already authenticates the payload
+            Some(_) => None,
+            None => Some(format!("{:x}", Sha256::digest(database))),
+        },
+        schema_version,
+    };
+    // base64 is ascii, so it's safe to split by bytes
+    let chunks = payload
+        .as_bytes()
+        .chunks(CHUNK_SIZE)
+        .map(|chunk| String::from_utf8(chunk.to_vec()).unwrap())
+        .collect::<Vec<String>>();
+    let chunks = if chunks.is_empty() { vec![String::new()] } else { chunks };
+    (manifest, chunks)
+}
+
+/// generate content of master post
+pub fn master_description(manifest: &Manifest, first_chunk: &str) -> String {
+    let manifest = base64::encode(serde_json::to_string(manifest).unwrap());
+    format!("{}{}:{}", MANIFEST_PREFIX, manifest, first_chunk)
+}
+
+/// parse content of master post
+pub fn parse_master(description: &str) -> Result<MasterPost, BlobError> {
+    let rest = match description.strip_prefix(MANIFEST_PREFIX) {
+        Some(rest) => rest,
+        None => return Ok(MasterPost::Legacy(description.to_string())),
+    };
+    let (manifest, first_chunk) = rest
+        .split_once(':')
+        .ok_or_else(|| BlobError::Manifest("missing separator".to_string()))?;
+    let manifest = base64::decode(manifest).map_err(BlobError::Decode)?;
+    let manifest: Manifest =
+        serde_json::from_slice(&manifest).map_err(|e| BlobError::Manifest(e.to_string()))?;
+    if manifest.version > MANIFEST_VERSION {
+        return Err(BlobError::Manifest(format!(
+            "version {} is newer than this cnblog supports, please upgrade cnblog",
+            manifest.version
+        )));
+    }
+    Ok(MasterPost::Manifest(manifest, first_chunk.to_string()))
+}
+
+/// decode joined chunks to database
+pub fn decode(manifest: &Manifest, payload: &str, passphrase: Option<&str>) -> Result<Vec<u8>, BlobError> {
+    // 1. verify payload
+    if payload.len() != manifest.length
+        || format!("{:x}", Sha256::digest(payload.as_bytes())) != manifest.sha256
+    {
+        return Err(BlobError::Checksum);
+    }
+
+    // 2. decrypt
+    let mut bytes = base64::decode(payload).map_err(BlobError::Decode)?;
+    if manifest.encrypted {
+        let passphrase = passphrase.ok_or(BlobError::NeedPassphrase)?;
+        bytes = crypto::decrypt(&bytes, passphrase).map_err(BlobError::Crypto)?;
+    }
+
+    // 3. decompress
+    if manifest.compression != "zlib" {
+        return Err(BlobError::Manifest(format!("unknown compression {}", manifest.compression)));
+    }
+    let mut database = Vec::new();
+    ZlibDecoder::new(bytes.as_slice())
+        .read_to_end(&mut database)
+        .map_err(BlobError::Decompress)?;
+
+    // 4. verify database
+    if let Some(db_sha256) = manifest.db_sha256.as_ref() {
+        if format!("{:x}", Sha256::digest(&database)) != *db_sha256 {
+            return Err(BlobError::Checksum);
+        }
+    }
+    Ok(database)
+}
+
+/// decode database written by older cnblog, it's plain base64
+pub fn decode_legacy(description: &str) -> Result<Vec<u8>, BlobError> {
+    base64::decode(description).map_err(BlobError::Decode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// encode and decode like uploading and downloading master post
+    fn round_trip(bytes: &[u8], passphrase: Option<&str>) -> Result<Vec<u8>, BlobError> {
+        let (mut manifest, chunks) = encode(bytes, passphrase, 0);
+        manifest.chunks = (1..chunks.len() as i32).collect();
+        let description = master_description(&manifest, &chunks[0]);
+        match parse_master(&description)? {
+            MasterPost::Manifest(manifest, first_chunk) => {
+                let payload = first_chunk + &chunks[1..].concat();
+                decode(&manifest, &payload, passphrase)
+            }
+            MasterPost::Legacy(_) => panic!("manifest is expected"),
+        }
+    }
+
+    #[test]
+    fn small_database() {
+        let bytes = b"SQLite format 3\0".repeat(100);
+        let (manifest, chunks) = encode(&bytes, None, 0);
+        assert_eq!(chunks.len(), 1);
+        assert!(manifest.length < bytes.len());
+        assert_eq!(round_trip(&bytes, None).unwrap(), bytes);
+    }
+
+    #[test]
+    fn chunked_database() {
+        // random bytes can't be compressed
+        let mut bytes = vec![0u8; CHUNK_SIZE];
+        getrandom::getrandom(&mut bytes).unwrap();
+        let (_, chunks) = encode(&bytes, None, 0);
+        assert!(chunks.len() > 1);
+        assert_eq!(round_trip(&bytes, None).unwrap(), bytes);
+    }
+
+    #[test]
+    fn encrypted_database() {
+        let bytes = b"blogs info".to_vec();
+        assert_eq!(round_trip(&bytes, Some("passphrase")).unwrap(), bytes);
+
+        let (manifest, chunks) = encode(&bytes, Some("passphrase"), 0);
+        assert!(manifest.encrypted);
+        // nothing in cleartext manifest is derived from the database
+        assert_eq!(manifest.db_sha256, None);
+        assert!(!master_description(&manifest, "").contains(&format!("{:x}", Sha256::digest(&bytes))));
+        assert!(matches!(decode(&manifest, &chunks[0], None), Err(BlobError::NeedPassphrase)));
+        assert!(matches!(decode(&manifest, &chunks[0], Some("wrong")), Err(BlobError::Crypto(_))));
+    }
+
+    #[test]
+    fn broken_chunk() {
+        let (manifest, chunks) = encode(b"blogs info", None, 0);
+        let payload = chunks.concat() + "AAAA";
+        assert!(matches!(decode(&manifest, &payload, None), Err(BlobError::Checksum)));
+    }
+
+    #[test]
+    fn database_checksum() {
+        let (mut manifest, chunks) = encode(b"blogs info", None, 3);
+        assert_eq!(manifest.schema_version, 3);
+        manifest.db_sha256 = Some("0".repeat(64));
+        assert!(matches!(decode(&manifest, &chunks[0], None), Err(BlobError::Checksum)));
+        // manifest written before database checksum
+        manifest.db_sha256 = None;
+        assert_eq!(decode(&manifest, &chunks[0], None).unwrap(), b"blogs info");
+    }
+
+    #[test]
+    fn legacy_database() {
+        let description = base64::encode(b"blogs info");
+        assert!(matches!(parse_master(&description), Ok(MasterPost::Legacy(_))));
+        assert_eq!(decode_legacy(&description).unwrap(), b"blogs info");
+    }
+}
@@ -0,0 +1,120 @@
+// content-addressed cache of post bodies, the last synced version of every blog
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::cfg::Utility;
+
+/// directory of cached posts in config directory
+pub const CACHE_DIR: &str = "cache";
+
+/// post bodies saved at `cache/<postid>/<hash>`
+pub struct PostCache {
+    dir: PathBuf,
+}
+
+impl PostCache {
+    /// open cache in config directory of database
+    pub fn open(database: &Path) -> PostCache {
+        PostCache {
+            dir: database.parent().unwrap().join(CACHE_DIR),
+        }
+    }
+
+    /// get content of post by its hash, a broken file is a miss
+    pub fn get(&self, postid: i32, hash: &str) -> Option<String> {
+        let content = fs::read_to_string(self.path(postid, hash)).ok()?;
+        if Utility::content_hash(content.as_str()) != hash {
+            return None;
+        }
+        Some(content)
+    }
+
+    /// save content of post and return its hash
+    pub fn put(&self, postid: i32, content: &str) -> io::Result<String> {
+        let hash = Utility::content_hash(content);
+        let path = self.path(postid, hash.as_str());
+        if !path.exists() {
+            fs::create_dir_all(path.parent().unwrap())?;
+            // a half written file never gets the name of a hash
+            let temp_path = path.with_extension("tmp");
+            fs::write(temp_path.as_path(), content)?;
+            fs::rename(temp_path, path)?;
+        }
+        Ok(hash)
+    }
+
+    /// remove every version but the last synced one, `live` maps postid to its hash
+    /// return the number of removed posts
+    pub fn prune(&self, live: &HashMap<i32, String>) -> io::Result<usize> {
+        let entries = match fs::read_dir(self.dir.as_path()) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+        let mut removed = 0;
+        for entry in entries {
+            let dir = entry?.path();
+            if !dir.is_dir() {
+                continue;
+            }
+            let hash = dir
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.parse::<i32>().ok())
+                .and_then(|postid| live.get(&postid));
+            for file in fs::read_dir(dir.as_path())? {
+                let file = file?.path();
+                if file.file_name().and_then(|name| name.to_str()) != hash.map(|hash| hash.as_str()) {
+                    fs::remove_file(file)?;
+                    removed += 1;
+                }
+            }
+            if hash.is_none() {
+                fs::remove_dir(dir)?;
+            }
+        }
+        Ok(removed)
+    }
+
+    fn path(&self, postid: i32, hash: &str) -> PathBuf {
+        self.dir.join(postid.to_string()).join(hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+
+    use super::PostCache;
+
+    #[test]
+    fn put_and_get() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = PostCache::open(dir.path().join("blogs_info.sqlite").as_path());
+        let hash = cache.put(1, "# cnblog").unwrap();
+        assert_eq!(cache.get(1, hash.as_str()).as_deref(), Some("# cnblog"));
+        assert_eq!(cache.get(2, hash.as_str()), None);
+
+        // a broken file isn't trusted
+        fs::write(cache.path(1, hash.as_str()), "# broken").unwrap();
+        assert_eq!(cache.get(1, hash.as_str()), None);
+    }
+
+    #[test]
+    fn prune_old_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = PostCache::open(dir.path().join("blogs_info.sqlite").as_path());
+        cache.put(1, "v1").unwrap();
+        let hash = cache.put(1, "v2").unwrap();
+        let deleted = cache.put(2, "deleted").unwrap();
+
+        let live = HashMap::from([(1, hash.clone())]);
+        assert_eq!(cache.prune(&live).unwrap(), 2);
+        assert_eq!(cache.get(1, hash.as_str()).as_deref(), Some("v2"));
+        assert_eq!(cache.get(2, deleted.as_str()), None);
+        assert!(!cache.path(2, deleted.as_str()).parent().unwrap().exists());
+    }
+}
@@ -1,31 +1,38 @@
 use std::collections::{BTreeMap, HashMap, HashSet};
-use std::fs::{self, File};
-use std::io::Write;
+use std::fs;
+use std::path::Path;
 use std::path::PathBuf;
-use std::{io::Read, path::Path};
+use std::time::Duration;
 
-use base64;
 use chrono::prelude::*;
 use filetime::FileTime;
 use regex::Regex;
 use rusqlite::{params, Connection, OpenFlags, Result};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tempfile::NamedTempFile;
 use walkdir::WalkDir;
-use xmlrpc::Error;
 
-use super::rpc::MetaWeblog;
+use super::backup;
+use super::blob::{self, BlobError, MasterPost};
+use super::cache::PostCache;
+use super::migration::{self, SCHEMA_VERSION};
+use super::rpc::{MetaWeblog, RpcError};
 use super::weblog::{Post, WpCategory};
 
 pub const BLOGS_INFO_CFG: &str = "blogs_info.sqlite";
 pub const USER_INFO_CFG: &str = "user_info.json";
 
 const MASTER_BLOGS_CFG: &str = "MASTER_CNBLOG_BLOGS_INFO_CFG";
+const MASTER_POST_TITLE: &str = "[CNBLOG]BLOGS_INFO_CFG";
+const CHUNK_POST_TITLE: &str = "[CNBLOG]BLOGS_INFO_CFG_CHUNK";
 
 /// user info config
 #[derive(Serialize, Deserialize, Debug)]
 pub struct UserInfo {
     pub username: String,
+    // password is saved in secret store, it's only read from user info written by older cnblog
+    #[serde(default, skip_serializing)]
     pub password: String,
     pub app_key : String,
     pub blogid: String,
@@ -45,6 +52,12 @@ pub struct Config {
     local_conn: Connection,
     // remote database conn
     cnblog_conn: Connection,
+    // passphrase to encrypt blogs info, None means no encryption
+    passphrase: Option<String>,
+    // postids of chunk posts of remote database
+    chunk_postids: Vec<i32>,
+    // last synced content of blogs
+    cache: PostCache,
 }
 
 impl Config {
@@ -68,34 +81,45 @@ impl Config {
         Config {
             weblog,
             master_postid,
+            cache: PostCache::open(blogs_path.as_path()),
             blogs_info_cfg_path: blogs_path,
             temp_data_file: NamedTempFile::new().unwrap(),
             local_conn: Connection::open_in_memory().unwrap(),
             cnblog_conn: Connection::open_in_memory().unwrap(),
+            passphrase: None,
+            chunk_postids: Vec::new(),
         }
     }
 
-    /// check username and password valid!
+    /// set metaweblog endpoint
+    pub fn set_endpoint(&mut self, endpoint: &str) {
+        self.weblog.set_endpoint(endpoint);
+    }
+
+    /// set timeouts of rpc requests
+    pub fn set_timeouts(&mut self, connect_timeout: Duration, read_timeout: Duration) {
+        self.weblog.set_timeouts(connect_timeout, read_timeout);
+    }
+
+    /// get passphrase of blogs info, it may be asked while downloading
+    pub fn passphrase(&self) -> Option<&str> {
+        self.passphrase.as_deref()
+    }
+
+    /// set passphrase to encrypt and decrypt blogs info
+    pub fn set_passphrase(&mut self, passphrase: Option<String>) {
+        self.passphrase = passphrase;
+    }
+
+    /// check username and password of weblog valid!
     /// Return Error while user info is wrong, else return
-    pub fn check_account(username: &str, password: &str, app_key: &str) -> Result<(), Error> {
-        let weblog = MetaWeblog::new(
-            username.to_string(),
-            password.to_string(),
-            username.to_string(),
-            app_key.to_string(),
-        );
+    pub fn check_account(weblog: &MetaWeblog) -> Result<(), RpcError> {
         weblog.get_users_blogs()?;
         Ok(())
     }
 
     /// try get master postid which that cantians blogs info
-    pub fn try_get_master_postid(username: &str, password: &str, app_key: &str) -> Result<i32, Error> {
-        let weblog = MetaWeblog::new(
-            username.to_string(),
-            password.to_string(),
-            "123".to_string(),
-            app_key.to_string(),
-        );
+    pub fn try_get_master_postid(weblog: &MetaWeblog) -> Result<i32, RpcError> {
         let categories = weblog.get_categories()?;
 
         // get "[随笔分类]%d[CNBLOG]" postid
@@ -148,22 +172,23 @@ impl Config {
             );",
             [],
         )?;
+
+        // upgrade to the latest schema
+        if let Err(e) = migration::migrate(&conn) {
+            panic!("{}", e);
+        }
         Ok(())
     }
 
     /// Upload a new blogs config file
     /// Will get a new postid for blogs info and generate a new category with postid
-    pub fn upload_new_blogs_cfg(username: &str, password: &str, app_key: &str, blogs_path: &Path) -> i32 {
+    pub fn upload_new_blogs_cfg(weblog: &MetaWeblog, blogs_path: &Path) -> i32 {
         // 1. get a new postid for blogs
-        let weblog = MetaWeblog::new(
-            username.to_string(),
-            password.to_string(),
-            "123".to_string(),
-            app_key.to_string(),
-        );
-        let mut post = Post::default();
-        post.title = "[CNBLOG]BLOGS_INFO_CFG".to_string();
-        post.description = "None".to_string();
+        let mut post = Post {
+            title: MASTER_POST_TITLE.to_string(),
+            description: "None".to_string(),
+            ..Default::default()
+        };
         let postid: i32 = weblog
             .new_post(post.clone(), false)
             .unwrap()
@@ -172,9 +197,10 @@ impl Config {
 
         // 2. upload new category
         let category = format!("{}[CNBLOG]", postid);
-        let mut wp_category = WpCategory::default();
-        wp_category.name = category.clone();
-        wp_category.parent_id = -1;
+        let wp_category = WpCategory {
+            name: category.clone(),
+            parent_id: -1,
+        };
         weblog.new_category(wp_category).unwrap();
 
         // 3. update local database
@@ -188,10 +214,12 @@ impl Config {
             params![MASTER_BLOGS_CFG, postid, now, 1],
         )
         .unwrap();
+        let schema_version = migration::version(&conn);
         drop(conn); // Saved database to upload file
 
         // 4. upload database
-        post.description = Config::file2base64(blogs_path);
+        let (manifest, chunks) = blob::encode(&fs::read(blogs_path).unwrap(), None, schema_version);
+        post.description = blob::master_description(&manifest, &chunks[0]);
         post.categories.push(category);
         weblog
             .edit_post(postid.to_string().as_str(), post, false)
@@ -201,8 +229,14 @@ impl Config {
     }
 
     /// download blogs info from cnblog to blogs_path
-    pub fn download_blogs_info(&self) {
-        self.download_blogs_info_to_path(self.blogs_info_cfg_path.as_path());
+    pub fn download_blogs_info(&self) -> Result<(), BlobError> {
+        self.download_blogs_info_to_path(self.blogs_info_cfg_path.as_path())?;
+        Ok(())
+    }
+
+    /// path of local blogs info database
+    pub fn blogs_info_cfg_path(&self) -> &Path {
+        self.blogs_info_cfg_path.as_path()
     }
 
     /// force to increase cfg timestamp for downloading all blogs
@@ -211,56 +245,103 @@ impl Config {
         local_conn.execute(r#"update BlogsInfo set timestamp = ? where postid=?"#, params![0, self.master_postid]).unwrap();
     }
 
-    fn download_blogs_info_to_path(&self, path: &Path) {
+    /// download blogs info to path
+    /// return postids of chunk posts
+    fn download_blogs_info_to_path(&self, path: &Path) -> Result<Vec<i32>, BlobError> {
         // 1. download blogs info
-        let post = self
-            .weblog
-            .get_post(self.master_postid.to_string().as_str())
-            .unwrap();
+        // it's the first call, so a rejected account is found here
+        let post = match self.weblog.get_post(self.master_postid.to_string().as_str()) {
+            Ok(post) => post,
+            Err(e) if e.fault().is_some() && self.is_account_rejected() => return Err(BlobError::Auth(e)),
+            Err(e) => return Err(BlobError::Master(e)),
+        };
 
-        // 2. decode and save
-        Config::base642file(post.description.as_str(), path);
+        // 2. join chunks and decode
+        let passphrase = self.passphrase.as_deref();
+        let (bytes, chunk_postids) = match blob::parse_master(post.description.as_str())? {
+            MasterPost::Manifest(manifest, mut payload) => {
+                // uploaded by a newer cnblog on another machine
+                if manifest.schema_version > SCHEMA_VERSION {
+                    return Err(BlobError::NewerSchema(manifest.schema_version));
+                }
+                for postid in manifest.chunks.iter() {
+                    let chunk = self
+                        .weblog
+                        .get_post(postid.to_string().as_str())
+                        .map_err(|e| BlobError::Chunk(*postid, e))?;
+                    payload.push_str(chunk.description.as_str());
+                }
+                (blob::decode(&manifest, &payload, passphrase)?, manifest.chunks)
+            }
+            MasterPost::Legacy(description) => (blob::decode_legacy(&description)?, Vec::new()),
+        };
+
+        // 3. save and validate
+        fs::write(path, bytes).unwrap();
+        backup::validate(path).map_err(BlobError::Invalid)?;
+        Ok(chunk_postids)
+    }
+
+    /// whether cnblog rejects the account
+    /// a fault of `getPost` may also mean the post is missing, so ask `getUsersBlogs` again
+    pub fn is_account_rejected(&self) -> bool {
+        match self.weblog.get_users_blogs() {
+            Ok(_) => false,
+            Err(e) => e.fault().is_some(),
+        }
     }
 
-    /// convert file to base64 string
-    fn file2base64(file_path: &Path) -> String {
-        // 1. read content
-        let mut f = File::open(file_path).unwrap();
-        let mut buffer = Vec::<u8>::new();
-        f.read_to_end(&mut buffer).unwrap();
+    /// whether a failed request of a post means the post is removed on cnblog website
+    pub fn is_post_missing(&self, e: &RpcError) -> bool {
+        e.fault().is_some() && !self.is_account_rejected()
+    }
 
-        // 2. base64 for content
-        let base = base64::encode(buffer);
-        base
+    /// use another account after password or app key is changed
+    pub fn set_account(&mut self, username: &str, password: &str, app_key: &str) {
+        self.weblog
+            .set_account(username.to_string(), password.to_string(), app_key.to_string());
     }
 
-    /// convert base64 to file
-    fn base642file(base: &str, file_path: &Path) {
-        // 1.decode base64
-        let bytes = base64::decode(base).unwrap();
+    /// learn chunk posts from remote manifest without downloading blogs info
+    /// it's used while remote blogs info can't be decoded but will be overwritten
+    pub fn load_chunk_postids(&mut self) {
+        let post = match self.weblog.get_post(self.master_postid.to_string().as_str()) {
+            Ok(post) => post,
+            Err(_) => return,
+        };
+        if let Ok(MasterPost::Manifest(manifest, _)) = blob::parse_master(post.description.as_str()) {
+            self.chunk_postids = manifest.chunks;
+        }
+    }
 
-        // 2. write file
-        let mut f = File::create(file_path).unwrap();
-        f.write_all(&bytes).unwrap();
+    /// generate a post which saves blogs info
+    fn blogs_info_post(master_postid: i32, title: &str, description: String) -> Post {
+        Post {
+            description,
+            title: title.to_string(),
+            categories: vec![format!("{}[CNBLOG]", master_postid)],
+            ..Default::default()
+        }
     }
 
     /// Write user basic info
-    pub fn write_user_info_cfg(username: &str, password: &str, app_key: &str, postid: i32, user_info_path: &Path) {
+    pub fn write_user_info_cfg(
+        weblog: &MetaWeblog,
+        username: &str,
+        password: &str,
+        app_key: &str,
+        postid: i32,
+        user_info_path: &Path,
+    ) {
         if user_info_path.exists() {
             println!(
                 "The {:?} file already exists!!!\nI'will overwrite it!",
                 user_info_path
             );
         }
-        // Get real blogid by using a fake value
-        let weblog = MetaWeblog::new(
-            username.to_string(),
-            password.to_string(),
-            "123".to_string(),
-            app_key.to_string(),
-        );
+        // Get real blogid, weblog uses a fake value
         let userblogs = weblog.get_users_blogs().unwrap();
-        let userblog = userblogs.get(0).unwrap();
+        let userblog = userblogs.first().unwrap();
         let blogid = userblog.blogid.clone();
 
         // Serialize User Info
@@ -271,9 +352,12 @@ impl Config {
             postid,
             blogid,
         };
-        let serialize = serde_json::to_string(&user_info).unwrap();
+        Config::save_user_info_cfg(&user_info, user_info_path);
+    }
 
-        // Write user info path
+    /// Write user info without password
+    pub fn save_user_info_cfg(user_info: &UserInfo, user_info_path: &Path) {
+        let serialize = serde_json::to_string(user_info).unwrap();
         fs::write(user_info_path, serialize).expect("Unable to write file for user_info");
     }
 
@@ -321,19 +405,34 @@ impl Config {
             eprintln!("local blogs info is newer than remote");
             return true;
         }
-        return false;
+        false
     }
 
     /// init Config loalc and remote Conn
-    pub fn init_conn(&mut self) {
+    pub fn init_conn(&mut self) -> Result<(), BlobError> {
         // 1. init local conn
-        self.local_conn = Connection::open(self.blogs_info_cfg_path.as_path()).unwrap();
+        self.open_local_conn()?;
 
         // 2. download blogs info
-        self.download_blogs_info_to_path(self.temp_data_file.path());
+        self.chunk_postids = self.download_blogs_info_to_path(self.temp_data_file.path())?;
 
         // 3. init remote blogs conn
         self.cnblog_conn = Connection::open(self.temp_data_file.path()).unwrap();
+        migration::migrate(&self.cnblog_conn).map_err(BlobError::Schema)?;
+        Ok(())
+    }
+
+    /// init local Conn only
+    pub fn open_local_conn(&mut self) -> Result<(), BlobError> {
+        self.local_conn = Connection::open(self.blogs_info_cfg_path.as_path()).unwrap();
+        let old_version = migration::migrate(&self.local_conn).map_err(BlobError::Schema)?;
+        if old_version < SCHEMA_VERSION {
+            println!(
+                "Info: local blogs info is upgraded from schema version {} to {}.",
+                old_version, SCHEMA_VERSION
+            );
+        }
+        Ok(())
     }
 
     /// get new blogs info by comparing local and remote database
@@ -348,7 +447,7 @@ impl Config {
             .filter(|(postid, _)| !local_blogs.contains_key(postid))
             .map(|(_, blog)| -> BlogsInfoDO { blog })
             .collect();
-        return new_blogs;
+        new_blogs
     }
 
     pub fn get_local_lost_blogs_info(&self, root_path: &str) -> Vec<BlogsInfoDO> {
@@ -404,7 +503,7 @@ impl Config {
     ) -> BTreeMap<i32, BlogsInfoDO> {
         // 1. prepare sql
         let sql = "\
-            select blog_path, postid, timestamp \
+            select blog_path, postid, timestamp, deleted, hash, state \
             from BlogsInfo "
             .to_string()
             + sql_suffix;
@@ -418,7 +517,9 @@ impl Config {
                     blog_path: row.get(0).unwrap(),
                     postid: row.get(1).unwrap(),
                     timestamp: row.get(2).unwrap(),
-                    deleted: false,
+                    deleted: row.get(3).unwrap(),
+                    hash: row.get(4).unwrap(),
+                    state: row.get(5).unwrap(),
                 })
             })
             .unwrap();
@@ -429,7 +530,7 @@ impl Config {
             let blog = blog.unwrap();
             btmap.insert(blog.postid, blog);
         });
-        return btmap;
+        btmap
     }
 
     /// get remote changed blogs info by comparing local and remote database
@@ -447,11 +548,11 @@ impl Config {
                 if let Some(local_blog_info) = local_blogs.get(postid) {
                     return remote_blog_info.timestamp > local_blog_info.timestamp;
                 }
-                return false;
+                false
             })
             .map(|(_, blog_info)| -> BlogsInfoDO { blog_info })
             .collect();
-        return changed_blogs;
+        changed_blogs
     }
 
     /// get remote deleted blogs but not local deleted blogs
@@ -465,14 +566,11 @@ impl Config {
             .into_iter()
             .filter(|(postid, _)| {
                 // compare existed local_blogs in not existed remote_blogs
-                if local_blogs.contains_key(postid) {
-                    return true;
-                }
-                return false;
+                local_blogs.contains_key(postid)
             })
             .map(|(_, blog_info)| -> BlogsInfoDO { blog_info })
             .collect();
-        return deleted_blogs;
+        deleted_blogs
     }
 
     /// overwrite local database from remote database
@@ -482,12 +580,12 @@ impl Config {
         self.local_conn.close().unwrap();
         self.cnblog_conn.close().unwrap();
 
-        // 2. mv old to old.bak and mv new to old
-        fs::rename(
-            &self.blogs_info_cfg_path,
-            self.blogs_info_cfg_path.with_extension("bak"),
-        )
-        .unwrap();
+        // 2. backup old and mv new to old
+        // remote database has been validated while downloading
+        match backup::backup(self.blogs_info_cfg_path.as_path()) {
+            Ok(path) => println!("Info: local blogs info is backed up to {:?}", path),
+            Err(e) => panic!("Can't backup local blogs info. Error: {}", e),
+        }
         if let Err(e) = fs::rename(&self.temp_data_file, self.blogs_info_cfg_path.as_path()) {
             eprintln!("Info: move file error: {e}");
             fs::copy(&self.temp_data_file, self.blogs_info_cfg_path.as_path()).unwrap();
@@ -515,18 +613,37 @@ impl Config {
             .unwrap();
 
         // 2. close local database
+        let schema_version = migration::version(&self.local_conn);
         self.local_conn.close().unwrap();
         self.cnblog_conn.close().unwrap();
 
-        // 2. upload(update) local database
-        let mut post = Post::default();
-        post.description = Config::file2base64(self.blogs_info_cfg_path.as_path());
-        post.title = "[CNBLOG]BLOGS_INFO_CFG".to_string();
-        post.categories
-            .push(format!("{}[CNBLOG]", self.master_postid));
+        // 3. encode local database
+        let bytes = fs::read(self.blogs_info_cfg_path.as_path()).unwrap();
+        let (mut manifest, chunks) = blob::encode(&bytes, self.passphrase.as_deref(), schema_version);
+
+        // 4. upload chunks, reuse old chunk posts
+        // keep blogs info posts as drafts, they're not articles
+        for (i, chunk) in chunks.iter().skip(1).enumerate() {
+            let post = Config::blogs_info_post(self.master_postid, CHUNK_POST_TITLE, chunk.clone());
+            let postid = match self.chunk_postids.get(i) {
+                Some(postid) if self.weblog.edit_post(postid.to_string().as_str(), post.clone(), false).is_ok() => *postid,
+                _ => self.weblog.new_post(post, false).unwrap().parse().unwrap(),
+            };
+            manifest.chunks.push(postid);
+        }
+
+        // 5. upload(update) master post
+        let post = Config::blogs_info_post(self.master_postid, MASTER_POST_TITLE, blob::master_description(&manifest, &chunks[0]));
         self.weblog
-            .edit_post(self.master_postid.to_string().as_str(), post, true)
+            .edit_post(self.master_postid.to_string().as_str(), post, false)
             .unwrap();
+
+        // 6. delete unused chunk posts
+        for postid in self.chunk_postids.iter().filter(|postid| !manifest.chunks.contains(postid)) {
+            if let Err(e) = self.weblog.delete_post(postid.to_string().as_str(), false) {
+                eprintln!("Warning: can't delete unused chunk post {}. Error: {}", postid, e);
+            }
+        }
     }
 
     /// get existing blogs path from local database
@@ -536,8 +653,15 @@ impl Config {
 
         // 2. get path
         blogs_info
-            .into_iter()
-            .map(|(_, blog_info)| -> String { blog_info.blog_path })
+            .into_values()
+            .map(|blog_info| -> String { blog_info.blog_path })
+            .collect()
+    }
+
+    /// get all existing blogs in local database
+    pub fn get_local_existed_blogs(&self) -> Vec<BlogsInfoDO> {
+        self.query_blogs_existed_info_do(&self.local_conn)
+            .into_values()
             .collect()
     }
 
@@ -549,11 +673,32 @@ impl Config {
 
         // 2. convert blog info to hashmap
         blogs_info
+            .into_values()
+            .map(|blog_info| (blog_info.blog_path, (blog_info.timestamp, blog_info.postid)))
+            .collect()
+    }
+
+    /// get every postid recorded in local database (including deleted blogs)
+    /// return map that key is postid and value is whether the blog is deleted
+    pub fn get_local_known_postids(&self) -> HashMap<i32, bool> {
+        self.query_blogs_info_do("", &self.local_conn)
             .into_iter()
-            .map(|(_, blog_info)| (blog_info.blog_path, (blog_info.timestamp, blog_info.postid)))
+            .map(|(postid, blog_info)| (postid, blog_info.deleted))
             .collect()
     }
 
+    /// get how a deleted blog was removed, None while it exists or isn't recorded
+    pub fn get_local_deleted_state(&self, postid: i32) -> Option<String> {
+        self.local_conn
+            .query_row(
+                "select state from BlogsInfo where postid = ? and deleted = 1",
+                [postid],
+                |row| row.get(0),
+            )
+            .ok()
+            .flatten()
+    }
+
     /// get all categories in local database
     pub fn get_local_categories(&self) -> HashSet<String> {
         let mut stmt = self
@@ -576,28 +721,89 @@ impl Config {
     }
 
     /// insert new blog
-    pub fn new_post(&self, blog_path: &str, postid: i32, timestamp: i64) {
+    pub fn new_post(&self, blog_path: &str, postid: i32, timestamp: i64, hash: &str) {
+        self.local_conn
+            .execute(
+                "insert into BlogsInfo (blog_path, postid, timestamp, deleted, hash) values (?, ?, ?, ?, ?)",
+                params![blog_path, postid, timestamp, 0, hash],
+            )
+            .unwrap();
+    }
+
+    /// adopt a remote blog which was not managed before
+    /// a blog deleted before will be recorded as existing again
+    pub fn adopt_post(&self, blog_path: &str, postid: i32, timestamp: i64, hash: &str) {
+        let updated = self
+            .local_conn
+            .execute(
+                "update BlogsInfo set blog_path = ?, timestamp = ?, deleted = 0, hash = ?, state = null where postid = ?",
+                params![blog_path, timestamp, hash, postid],
+            )
+            .unwrap();
+        if updated == 0 {
+            self.new_post(blog_path, postid, timestamp, hash);
+        }
+    }
+
+    /// update changed blog's timestamp and content hash
+    pub fn edit_post(&self, postid: i32, timestamp: i64, hash: &str) {
+        self.local_conn
+            .execute(
+                "update BlogsInfo set timestamp = ?, hash = ? where postid = ?",
+                params![timestamp, hash, postid],
+            )
+            .unwrap();
+    }
+
+    /// record content hash of blog without touching its timestamp
+    pub fn set_post_hash(&self, postid: i32, hash: &str) {
         self.local_conn
             .execute(
-                "insert into BlogsInfo (blog_path, postid, timestamp, deleted) values (?, ?, ?, ?)",
-                params![blog_path, postid, timestamp, 0],
+                "update BlogsInfo set hash = ? where postid = ?",
+                params![hash, postid],
             )
             .unwrap();
     }
 
-    /// update changed blog's timestamp
-    pub fn edit_post(&self, postid: i32, timestamp: i64) {
+    /// move blog record to a re-created remote post
+    pub fn replace_postid(&self, old_postid: i32, new_postid: i32, timestamp: i64, hash: &str) {
         self.local_conn
             .execute(
-                "update BlogsInfo set timestamp = ? where postid = ?",
-                params![timestamp, postid],
+                "update BlogsInfo set postid = ?, timestamp = ?, hash = ? where postid = ?",
+                params![new_postid, timestamp, hash, old_postid],
             )
             .unwrap();
     }
 
-    /// delete blog by postid
-    pub fn delete_post(&self, postid: i32) {
-        self.local_conn.execute(r#"update BlogsInfo set deleted = 1 where postid = ?"#, [postid]).unwrap();
+    /// cache content of a synced blog, it's the merge base of later changes
+    pub fn cache_post(&self, postid: i32, content: &str) {
+        if let Err(e) = self.cache.put(postid, content) {
+            eprintln!("Warning: can't cache post {}. Error: {}", postid, e);
+        }
+    }
+
+    /// get cached content of blog by its hash
+    pub fn cached_post(&self, postid: i32, hash: &str) -> Option<String> {
+        self.cache.get(postid, hash)
+    }
+
+    /// remove cached blogs which are not the last synced version in local database
+    pub fn prune_cache(&self) {
+        let live: HashMap<i32, String> = self
+            .query_blogs_existed_info_do(&self.local_conn)
+            .into_iter()
+            .filter_map(|(postid, blog_info)| blog_info.hash.map(|hash| (postid, hash)))
+            .collect();
+        if let Err(e) = self.cache.prune(&live) {
+            eprintln!("Warning: can't prune cached posts. Error: {}", e);
+        }
+    }
+
+    /// delete blog by postid, `state` records how the remote post was removed
+    pub fn delete_post(&self, postid: i32, state: &str) {
+        self.local_conn
+            .execute(r#"update BlogsInfo set deleted = 1, state = ? where postid = ?"#, params![state, postid])
+            .unwrap();
     }
 }
 
@@ -606,6 +812,10 @@ pub struct BlogsInfoDO {
     pub postid: i32,
     pub timestamp: i64,
     pub deleted: bool,
+    // sha256 of last uploaded content, None while it's recorded by older cnblog
+    pub hash: Option<String>,
+    // how the deleted blog was removed, None while it exists
+    pub state: Option<String>,
 }
 
 /// function for utility
@@ -619,6 +829,11 @@ impl Utility {
         mtime.unix_seconds()
     }
 
+    /// get sha256 of blog content
+    pub fn content_hash(content: &str) -> String {
+        format!("{:x}", Sha256::digest(content.as_bytes()))
+    }
+
     /// modify file mtime
     pub fn modify_file_timestamp(file_path: &Path, timestamp: i64) {
         let mtime = FileTime::from_unix_time(timestamp, 0);
@@ -628,6 +843,9 @@ impl Utility {
 
 #[cfg(test)]
 mod config_test {
+    use rusqlite::Connection;
+
+    use super::{migration, Config, Utility, SCHEMA_VERSION};
 
     #[test]
     fn test_check_account() {
@@ -636,4 +854,25 @@ mod config_test {
     #[test]
     fn try_get_master_postid() {
     }
+
+    #[test]
+    fn create_latest_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = dir.path().join("blogs_info.sqlite");
+        Config::create_database(database.as_path()).unwrap();
+        let conn = Connection::open(database.as_path()).unwrap();
+        assert_eq!(migration::version(&conn), SCHEMA_VERSION);
+        conn.execute("insert into BlogsInfo (blog_path, postid, timestamp, deleted, hash) values ('a.md', 1, 1, 0, 'h')", [])
+            .unwrap();
+    }
+
+    #[test]
+    fn content_hash() {
+        assert_eq!(
+            Utility::content_hash("cnblog"),
+            Utility::content_hash("cnblog")
+        );
+        assert_ne!(Utility::content_hash("cnblog"), Utility::content_hash("cnblog "));
+        assert_eq!(Utility::content_hash("").len(), 64);
+    }
 }
@@ -0,0 +1,131 @@
+// encrypt blogs info with a passphrase, or data with a random raw key
+use std::fmt;
+
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hmac::Hmac;
+use sha2::Sha256;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+pub const KEY_LEN: usize = 32;
+const PBKDF2_ROUNDS: u32 = 100_000;
+
+#[derive(Debug, PartialEq)]
+pub enum CryptoError {
+    /// data is too short to be encrypted by cnblog
+    Malformed,
+    /// passphrase is wrong or data was modified
+    WrongPassphrase,
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CryptoError::Malformed => write!(f, "encrypted data is malformed"),
+            CryptoError::WrongPassphrase => {
+                write!(f, "can't decrypt data, the passphrase may be wrong")
+            }
+        }
+    }
+}
+
+/// encrypt data, return `salt | nonce | ciphertext`
+pub fn encrypt(data: &[u8], passphrase: &str) -> Vec<u8> {
+    // 1. random salt and nonce
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    getrandom::getrandom(&mut salt).unwrap();
+    getrandom::getrandom(&mut nonce).unwrap();
+
+    // 2. encrypt
+    let mut result = salt.to_vec();
+    result.extend_from_slice(&seal(data, &derive_key(passphrase, &salt), &nonce));
+    result
+}
+
+/// encrypt data with a random key of `KEY_LEN` bytes, return `nonce | ciphertext`
+/// the key isn't derived, so it must not be a passphrase
+pub fn encrypt_with_key(data: &[u8], key: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    if key.len() != KEY_LEN {
+        return Err(CryptoError::Malformed);
+    }
+    let mut nonce = [0u8; NONCE_LEN];
+    getrandom::getrandom(&mut nonce).unwrap();
+    Ok(seal(data, Key::from_slice(key), &nonce))
+}
+
+/// decrypt data generated by `encrypt_with_key`
+pub fn decrypt_with_key(data: &[u8], key: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    if key.len() != KEY_LEN {
+        return Err(CryptoError::Malformed);
+    }
+    open(data, Key::from_slice(key))
+}
+
+/// decrypt data generated by `encrypt`
+pub fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>, CryptoError> {
+    if data.len() < SALT_LEN + NONCE_LEN {
+        return Err(CryptoError::Malformed);
+    }
+    let (salt, data) = data.split_at(SALT_LEN);
+    open(data, &derive_key(passphrase, salt))
+}
+
+/// return `nonce | ciphertext`
+fn seal(data: &[u8], key: &Key, nonce: &[u8]) -> Vec<u8> {
+    let cipher = ChaCha20Poly1305::new(key);
+    let ciphertext = cipher.encrypt(Nonce::from_slice(nonce), data).unwrap();
+    let mut result = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+    result.extend_from_slice(nonce);
+    result.extend_from_slice(&ciphertext);
+    result
+}
+
+/// decrypt `nonce | ciphertext`
+fn open(data: &[u8], key: &Key) -> Result<Vec<u8>, CryptoError> {
+    if data.len() < NONCE_LEN {
+        return Err(CryptoError::Malformed);
+    }
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    let cipher = ChaCha20Poly1305::new(key);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| CryptoError::WrongPassphrase)
+}
+
+/// derive key from passphrase by PBKDF2-HMAC-SHA256
+fn derive_key(passphrase: &str, salt: &[u8]) -> Key {
+    let mut key = [0u8; KEY_LEN];
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(passphrase.as_bytes(), salt, PBKDF2_ROUNDS, &mut key);
+    Key::clone_from_slice(&key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decrypt, decrypt_with_key, encrypt, encrypt_with_key, CryptoError, KEY_LEN};
+
+    #[test]
+    fn round_trip() {
+        let data = b"SQLite format 3\0";
+        let encrypted = encrypt(data, "passphrase");
+        assert_ne!(&encrypted[..], &data[..]);
+        assert_eq!(decrypt(&encrypted, "passphrase").unwrap(), data);
+    }
+
+    #[test]
+    fn wrong_passphrase() {
+        let encrypted = encrypt(b"blogs info", "passphrase");
+        assert_eq!(decrypt(&encrypted, "wrong"), Err(CryptoError::WrongPassphrase));
+        assert_eq!(decrypt(b"short", "passphrase"), Err(CryptoError::Malformed));
+    }
+
+    #[test]
+    fn raw_key() {
+        let key = [7u8; KEY_LEN];
+        let encrypted = encrypt_with_key(b"password", &key).unwrap();
+        assert_eq!(decrypt_with_key(&encrypted, &key).unwrap(), b"password");
+        assert_eq!(decrypt_with_key(&encrypted, &[8u8; KEY_LEN]), Err(CryptoError::WrongPassphrase));
+        assert_eq!(encrypt_with_key(b"password", b"short"), Err(CryptoError::Malformed));
+    }
+}
@@ -0,0 +1,137 @@
+// schema migrations of blogs info database, version is saved in `PRAGMA user_version`
+use std::fmt;
+
+use rusqlite::{Connection, Transaction};
+
+/// schema version written by this cnblog
+pub const SCHEMA_VERSION: i32 = 2;
+
+/// `MIGRATIONS[i]` upgrades schema from version `i` to `i + 1`
+const MIGRATIONS: [fn(&Transaction) -> rusqlite::Result<()>; SCHEMA_VERSION as usize] = [add_hash_column, add_state_column];
+
+#[derive(Debug)]
+pub enum MigrationError {
+    /// database was written by a newer cnblog
+    Newer(i32),
+    /// migration failed, database is rolled back
+    Sqlite(rusqlite::Error),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::Newer(version) => write!(
+                f,
+                "blogs info uses schema version {}, but this cnblog only supports version {}, please upgrade cnblog",
+                version, SCHEMA_VERSION
+            ),
+            MigrationError::Sqlite(e) => write!(f, "can't migrate blogs info: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+/// get schema version of database
+pub fn version(conn: &Connection) -> i32 {
+    conn.query_row("pragma user_version", [], |row| row.get(0))
+        .unwrap()
+}
+
+/// upgrade database to `SCHEMA_VERSION`, a newer database is never downgraded
+/// return schema version before migrating
+pub fn migrate(conn: &Connection) -> Result<i32, MigrationError> {
+    let old_version = version(conn);
+    if old_version > SCHEMA_VERSION {
+        return Err(MigrationError::Newer(old_version));
+    }
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(old_version as usize) {
+        // every step is committed with its version, so a failed step keeps the older schema
+        let tx = conn.unchecked_transaction().map_err(MigrationError::Sqlite)?;
+        migration(&tx).map_err(MigrationError::Sqlite)?;
+        tx.pragma_update(None, "user_version", i as i32 + 1)
+            .map_err(MigrationError::Sqlite)?;
+        tx.commit().map_err(MigrationError::Sqlite)?;
+    }
+    Ok(old_version)
+}
+
+/// v1: sha256 of last uploaded content
+/// databases created before versioning may already have it
+fn add_hash_column(tx: &Transaction) -> rusqlite::Result<()> {
+    let mut stmt = tx.prepare("pragma table_info(BlogsInfo)")?;
+    let has_hash = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .any(|name| name.map(|name| name == "hash").unwrap_or(false));
+    if !has_hash {
+        tx.execute("alter table BlogsInfo add column hash text", [])?;
+    }
+    Ok(())
+}
+
+/// v2: how a deleted blog was removed (`deleted`, `unpublished` or `archived`)
+/// blogs deleted before are recorded as `deleted`
+fn add_state_column(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute("alter table BlogsInfo add column state text", [])?;
+    tx.execute("update BlogsInfo set state = 'deleted' where deleted = 1", [])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// schema created by cnblog before migrations
+    fn legacy_database() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "create table BlogsInfo (id integer primary key, blog_path nvarchar, postid integer, timestamp integer, deleted BOOLEAN)",
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn migrate_legacy_database() {
+        let conn = legacy_database();
+        assert_eq!(migrate(&conn).unwrap(), 0);
+        assert_eq!(version(&conn), SCHEMA_VERSION);
+        // nothing to do at the second time
+        assert_eq!(migrate(&conn).unwrap(), SCHEMA_VERSION);
+        conn.execute("insert into BlogsInfo (blog_path, postid, timestamp, deleted, hash) values ('a.md', 1, 1, 0, 'h')", [])
+            .unwrap();
+    }
+
+    #[test]
+    fn deleted_blogs_get_state() {
+        let conn = legacy_database();
+        conn.execute("insert into BlogsInfo (blog_path, postid, timestamp, deleted) values ('a.md', 1, 1, 1)", [])
+            .unwrap();
+        conn.execute("insert into BlogsInfo (blog_path, postid, timestamp, deleted) values ('b.md', 2, 1, 0)", [])
+            .unwrap();
+        migrate(&conn).unwrap();
+        let state = |postid: i32| -> Option<String> {
+            conn.query_row("select state from BlogsInfo where postid = ?", [postid], |row| row.get(0))
+                .unwrap()
+        };
+        assert_eq!(state(1).as_deref(), Some("deleted"));
+        assert_eq!(state(2), None);
+    }
+
+    #[test]
+    fn hash_column_already_exists() {
+        let conn = legacy_database();
+        conn.execute("alter table BlogsInfo add column hash text", []).unwrap();
+        assert_eq!(migrate(&conn).unwrap(), 0);
+        assert_eq!(version(&conn), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn refuse_downgrade() {
+        let conn = legacy_database();
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1).unwrap();
+        assert!(matches!(migrate(&conn), Err(MigrationError::Newer(v)) if v == SCHEMA_VERSION + 1));
+        assert_eq!(version(&conn), SCHEMA_VERSION + 1);
+    }
+}
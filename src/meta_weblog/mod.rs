@@ -1,3 +1,9 @@
 pub mod weblog;
 pub mod rpc;
+pub mod backup;
+pub mod blob;
+pub mod cache;
 pub mod cfg;
+pub mod crypto;
+pub mod migration;
+pub mod value;
@@ -1,4 +1,8 @@
-use std::fs::File;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Cursor;
+use std::time::Duration;
 
 use chrono::Timelike;
 use chrono::Datelike;
@@ -6,9 +10,12 @@ use crate::meta_weblog::weblog::WpCategory;
 use crate::BlogInfo;
 use crate::CategoryInfo;
 use iso8601::DateTime;
-use xmlrpc::{Error, Request, Value};
+use reqwest::blocking::{Client, RequestBuilder};
+use serde::Serialize;
+use xmlrpc::{http, Error, Fault, Request, Transport, Value};
 
-use super::weblog::Post;
+use super::value::{from_value, to_params, Raw};
+use super::weblog::{try_from_array, Post};
 
 const DELETE_POST: &str = "blogger.deletePost";
 const EDIT_POST: &str = "metaWeblog.editPost";
@@ -18,196 +25,393 @@ const GET_RECENT_POSTS: &str = "metaWeblog.getRecentPosts";
 const GET_USERS_BLOGS: &str = "blogger.getUsersBlogs";
 const NEW_POST: &str = "metaWeblog.newPost";
 const NEW_CATEGORY: &str = "wp.newCategory";
+const LIST_METHODS: &str = "system.listMethods";
+const MULTICALL: &str = "system.multicall";
+/// calls in one `system.multicall` request
+const MULTICALL_SIZE: usize = 50;
 const SERVER_URL: &str = "https://rpc.cnblogs.com/metaweblog";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// error of a rpc call
+#[derive(Debug)]
+pub enum RpcError {
+    /// request fails or the server answers a fault
+    Call(Error),
+    /// parameters can't be converted to xmlrpc values
+    Params { method: String, message: String },
+    /// response isn't the expected type
+    Response { method: String, message: String },
+    /// a call in `system.multicall` answers a fault
+    Fault { method: String, fault: Fault },
+    /// connecting or waiting for the response takes longer than the timeout
+    Timeout { method: String, connect: bool, timeout: Duration },
+}
+
+impl RpcError {
+    /// fault answered by the server, e.g. the account is rejected or the post doesn't exist
+    pub fn fault(&self) -> Option<&Fault> {
+        match self {
+            RpcError::Call(e) => e.fault(),
+            RpcError::Fault { fault, .. } => Some(fault),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RpcError::Call(e) => write!(f, "{}", e),
+            RpcError::Params { method, message } => write!(f, "invalid parameters of {}: {}", method, message),
+            RpcError::Response { method, message } => write!(f, "unexpected response of {}: {}", method, message),
+            RpcError::Fault { method, fault } => write!(f, "{} fails: {}", method, fault),
+            RpcError::Timeout { method, connect, timeout } => write!(
+                f,
+                "{} timed out after {}s while {}, check the network or raise `{}` in settings.toml",
+                method,
+                timeout.as_secs(),
+                if *connect { "connecting" } else { "waiting for the response" },
+                if *connect { "connect_timeout" } else { "read_timeout" }
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+impl From<Error> for RpcError {
+    fn from(e: Error) -> Self {
+        RpcError::Call(e)
+    }
+}
+
+/// typed result of a rpc call
+pub trait FromResponse: Sized {
+    fn from_response(value: Value) -> Result<Self, String>;
+}
+
+// raw response
+impl FromResponse for Value {
+    fn from_response(value: Value) -> Result<Self, String> {
+        Ok(value)
+    }
+}
+
+// `Deserialize` types, e.g. postid, bool of edit and delete
+macro_rules! serde_response {
+    ($($ty:ty),*) => {
+        $(
+            impl FromResponse for $ty {
+                fn from_response(value: Value) -> Result<Self, String> {
+                    from_value(value).map_err(|e| e.to_string())
+                }
+            }
+        )*
+    };
+}
+
+serde_response!(String, i32, i64, bool, f64, Vec<String>);
+
+// `Convert` structs and arrays of them
+macro_rules! convert_response {
+    ($($ty:ty),*) => {
+        $(
+            impl FromResponse for $ty {
+                fn from_response(value: Value) -> Result<Self, String> {
+                    <$ty>::try_from(value).map_err(|e| e.to_string())
+                }
+            }
+
+            impl FromResponse for Vec<$ty> {
+                fn from_response(value: Value) -> Result<Self, String> {
+                    try_from_array(value).map_err(|e| e.to_string())
+                }
+            }
+        )*
+    };
+}
+
+convert_response!(Post, BlogInfo, CategoryInfo);
 
 pub struct MetaWeblog {
     app_key: String,
     username: String,
     password: String,
     blogid: String,
+    endpoint: String,
     url: String,
+    /// whether `system.multicall` is supported, None until it's detected
+    multicall: Cell<Option<bool>>,
+    /// http client shared by calls, so connections are kept alive
+    client: Client,
+    connect_timeout: Duration,
+    read_timeout: Duration,
+}
+
+/// reqwest transport which remembers a timeout, xmlrpc hides the error of transport
+struct HttpTransport<'a> {
+    builder: RequestBuilder,
+    /// Some(true) while connecting times out, Some(false) while reading
+    timed_out: &'a Cell<Option<bool>>,
+}
+
+impl Transport for HttpTransport<'_> {
+    type Stream = Cursor<Vec<u8>>;
+
+    fn transmit(self, request: &Request<'_>) -> Result<Self::Stream, Box<dyn std::error::Error + Send + Sync>> {
+        let check_timeout = |e: reqwest::Error| {
+            if e.is_timeout() {
+                self.timed_out.set(Some(e.is_connect()));
+            }
+            e
+        };
+        // 1. send request like the transport of xmlrpc
+        let mut body = Vec::new();
+        request.write_as_xml(&mut body).unwrap();
+        let response = http::build_headers(self.builder, body.len() as u64)
+            .body(body)
+            .send()
+            .map_err(check_timeout)?;
+        http::check_response(&response)?;
+
+        // 2. read the whole response, so a read timeout is found here
+        let bytes = response.bytes().map_err(check_timeout)?;
+        Ok(Cursor::new(bytes.to_vec()))
+    }
 }
 
 impl MetaWeblog {
     // new
     pub fn new(username: String, password: String, blogid: String, app_key: String) -> Self {
         MetaWeblog {
+            endpoint: SERVER_URL.to_string(),
             url: format!("{}/{}", SERVER_URL, app_key.as_str()),
             password,
             blogid,
             app_key,
             username,
+            multicall: Cell::new(None),
+            client: Self::client(CONNECT_TIMEOUT, READ_TIMEOUT),
+            connect_timeout: CONNECT_TIMEOUT,
+            read_timeout: READ_TIMEOUT,
         }
     }
 
-    pub fn new_post(&self, mut post: Post, publish: bool) -> Result<String, Error> {
-        if post.dateCreated == DateTime::default() {
-            post.dateCreated = Self::get_now_time();
-        }
-        // 1. geerate arguments
-        let mut arguments = Vec::<Value>::new();
-        arguments.push(Value::String(self.blogid.to_string()));
-        arguments.push(Value::String(self.username.to_string()));
-        arguments.push(Value::String(self.password.to_string()));
-        arguments.push(post.into());
-        arguments.push(Value::Bool(publish));
-
-        // 2. call rpc
-        let result = self.rpc_request(NEW_POST, arguments)?;
+    /// set timeouts of connecting and of waiting for a whole response
+    pub fn set_timeouts(&mut self, connect_timeout: Duration, read_timeout: Duration) {
+        self.client = Self::client(connect_timeout, read_timeout);
+        self.connect_timeout = connect_timeout;
+        self.read_timeout = read_timeout;
+    }
 
-        // 3. parse result
-        if let Value::String(postid) = result {
-            return Ok(postid);
-        }
-        Ok("-2".to_string())
+    /// http client keeping connections alive
+    /// proxies are read from `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` by reqwest
+    fn client(connect_timeout: Duration, read_timeout: Duration) -> Client {
+        Client::builder()
+            .connect_timeout(connect_timeout)
+            .timeout(read_timeout)
+            .tcp_keepalive(Duration::from_secs(60))
+            .build()
+            .unwrap()
     }
 
-    pub fn new_category(&self, category: WpCategory) -> Result<i32, Error> {
-        // 1. geerate arguments
-        let mut arguments = Vec::<Value>::new();
-        arguments.push(Value::String(self.blogid.to_string()));
-        arguments.push(Value::String(self.username.to_string()));
-        arguments.push(Value::String(self.password.to_string()));
-        arguments.push(category.into());
+    /// use another metaweblog endpoint instead of cnblog's
+    pub fn set_endpoint(&mut self, endpoint: &str) {
+        self.endpoint = endpoint.trim_end_matches('/').to_string();
+        self.url = format!("{}/{}", self.endpoint, self.app_key.as_str());
+        self.multicall.set(None);
+    }
 
-        // 2. call rpc
-        let result = self.rpc_request(NEW_CATEGORY, arguments)?;
+    /// use another account (after password or app key is changed), blogid is kept
+    pub fn set_account(&mut self, username: String, password: String, app_key: String) {
+        self.url = format!("{}/{}", self.endpoint, app_key.as_str());
+        self.username = username;
+        self.password = password;
+        self.app_key = app_key;
+    }
 
-        // 3. parse result
-        if let Value::Int(categoryid) = result {
-            return Ok(categoryid);
-        }
-        Ok(-1)
+    /// new post, return its postid
+    pub fn new_post(&self, post: Post, publish: bool) -> Result<String, RpcError> {
+        let post = Self::post_value(post);
+        self.call(NEW_POST, &(&self.blogid, &self.username, &self.password, Raw(&post), publish))
     }
 
-    pub fn get_post(&self, postid: &str) -> Result<Post, Error> {
-        // 1. geerate arguments
-        let mut arguments = Vec::<Value>::new();
-        arguments.push(Value::String(postid.to_string()));
-        arguments.push(Value::String(self.username.to_string()));
-        arguments.push(Value::String(self.password.to_string()));
+    /// new category, return its id
+    pub fn new_category(&self, category: WpCategory) -> Result<i32, RpcError> {
+        let category = Value::from(category);
+        self.call(NEW_CATEGORY, &(&self.blogid, &self.username, &self.password, Raw(&category)))
+    }
 
-        // 2. call rpc
-        let result = self.rpc_request(GET_POST, arguments)?;
+    pub fn get_post(&self, postid: &str) -> Result<Post, RpcError> {
+        self.call(GET_POST, &(postid, &self.username, &self.password))
+    }
 
-        // 3. parse result
-        let post = Post::from(result);
-        Ok(post)
+    pub fn get_recent_posts(&self, num: u32) -> Result<Vec<Post>, RpcError> {
+        self.call(GET_RECENT_POSTS, &(&self.blogid, &self.username, &self.password, num))
     }
 
-    pub fn get_recent_posts(&self, num: u32) -> Result<Vec<Post>, Error> {
-        // 1. geerate arguments
-        let mut arguments = Vec::<Value>::new();
-        arguments.push(Value::String(self.blogid.to_string()));
-        arguments.push(Value::String(self.username.to_string()));
-        arguments.push(Value::String(self.password.to_string()));
-        arguments.push(Value::Int(num as i32));
+    pub fn get_categories(&self) -> Result<Vec<CategoryInfo>, RpcError> {
+        self.call(GET_CATEGORIES, &(&self.blogid, &self.username, &self.password))
+    }
 
-        // 2. call rpc
-        let result = self.rpc_request(GET_RECENT_POSTS, arguments)?;
+    pub fn get_users_blogs(&self) -> Result<Vec<BlogInfo>, RpcError> {
+        self.call(GET_USERS_BLOGS, &(&self.app_key, &self.username, &self.password))
+    }
 
-        // 3. parse result
-        let mut posts = Vec::<Post>::new();
-        if let Value::Array(results) = result {
-            for v in results.into_iter() {
-                posts.push(v.into());
-            }
-        }
-        Ok(posts)
+    pub fn edit_post(&self, postid: &str, post: Post, publish: bool) -> Result<bool, RpcError> {
+        let post = Self::post_value(post);
+        self.call(EDIT_POST, &(postid, &self.username, &self.password, Raw(&post), publish))
     }
 
-    pub fn get_categories(&self) -> Result<Vec<CategoryInfo>, Error> {
-        // 1. generate arguments
-        let mut args = Vec::<Value>::new();
-        args.push(Value::String(self.blogid.to_string()));
-        args.push(Value::String(self.username.to_string()));
-        args.push(Value::String(self.password.to_string()));
+    /// download posts in batches, results are in order of `postids`
+    pub fn get_posts(&self, postids: &[String]) -> Vec<Result<Post, RpcError>> {
+        let params: Vec<_> = postids
+            .iter()
+            .map(|postid| (postid, &self.username, &self.password))
+            .collect();
+        self.call_many(GET_POST, &params)
+    }
 
-        // 2. call url
-        let result = self.rpc_request(GET_CATEGORIES, args)?;
+    /// upload posts of (postid, post) in batches, results are in order of `posts`
+    pub fn edit_posts(&self, posts: Vec<(String, Post)>, publish: bool) -> Vec<Result<bool, RpcError>> {
+        let posts: Vec<(String, Value)> = posts
+            .into_iter()
+            .map(|(postid, post)| (postid, Self::post_value(post)))
+            .collect();
+        let params: Vec<_> = posts
+            .iter()
+            .map(|(postid, post)| (postid, &self.username, &self.password, Raw(post), publish))
+            .collect();
+        self.call_many(EDIT_POST, &params)
+    }
 
-        // 3. parse result
-        let mut categories = Vec::<CategoryInfo>::new();
-        if let Value::Array(results) = result {
-            for v in results.into_iter() {
-                let category = CategoryInfo::from(v);
-                categories.push(category);
-            }
-        }
-        Ok(categories)
+    /// Delete post by postid
+    pub fn delete_post(&self, postid: &str, publish: bool) -> Result<bool, RpcError> {
+        self.call(DELETE_POST, &(&self.app_key, postid, &self.username, &self.password, publish))
     }
 
-    pub fn get_users_blogs(&self) -> Result<Vec<BlogInfo>, Error> {
-        // 1. generate arguments
-        let mut args = Vec::<Value>::new();
-        args.push(Value::String(self.app_key.clone()));
-        args.push(Value::String(self.username.clone()));
-        args.push(Value::String(self.password.clone()));
+    /// call `method` with a tuple of parameters, response is converted to `R`
+    pub fn call<P: Serialize + ?Sized, R: FromResponse>(&self, method: &str, params: &P) -> Result<R, RpcError> {
+        // 1. convert parameters
+        let params = to_params(params).map_err(|e| RpcError::Params {
+            method: method.to_string(),
+            message: e.to_string(),
+        })?;
 
         // 2. call rpc
-        let result = self.rpc_request(GET_USERS_BLOGS, args)?;
-
-        // 3. parse result
-        let mut blog_infos = Vec::<BlogInfo>::new();
-        if let Value::Array(results) = result {
-            for v in results {
-                let blog_info = BlogInfo::from(v);
-                blog_infos.push(blog_info);
+        let result = self.rpc_request(method, params)?;
+
+        // 3. convert result
+        R::from_response(result).map_err(|message| RpcError::Response { method: method.to_string(), message })
+    }
+
+    /// call `method` once for every parameters
+    /// calls are sent in batches by `system.multicall` if the server supports it, else one by one
+    /// results are in order of `params`, a failed call doesn't fail the others
+    pub fn call_many<P: Serialize, R: FromResponse>(&self, method: &str, params: &[P]) -> Vec<Result<R, RpcError>> {
+        if params.len() < 2 || !self.supports_multicall() {
+            return params.iter().map(|params| self.call(method, params)).collect();
+        }
+        let mut results = Vec::with_capacity(params.len());
+        for chunk in params.chunks(MULTICALL_SIZE) {
+            match self.multicall(method, chunk) {
+                Ok(chunk_results) => results.extend(chunk_results),
+                Err(e) => {
+                    // the server refuses multicall, don't try it again
+                    if e.fault().is_some() {
+                        self.multicall.set(Some(false));
+                    }
+                    eprintln!("Warning: {} fails, call {} one by one. Error: {}", MULTICALL, method, e);
+                    results.extend(chunk.iter().map(|params| self.call(method, params)));
+                }
             }
         }
-        Ok(blog_infos)
+        results
     }
 
-    pub fn edit_post(&self, postid: &str, mut post: Post, publish: bool) -> Result<Value, Error> {
-        if post.dateCreated == DateTime::default() {
-            post.dateCreated = Self::get_now_time();
+    /// whether the server supports `system.multicall`, it's asked once by `system.listMethods`
+    fn supports_multicall(&self) -> bool {
+        if let Some(supported) = self.multicall.get() {
+            return supported;
         }
-        // 1. generate parameters
-        let mut arguments = Vec::<Value>::new();
-        arguments.push(Value::String(postid.to_string()));
-        arguments.push(Value::String(self.username.to_string()));
-        arguments.push(Value::String(self.password.to_string()));
-        arguments.push(post.into());
-        arguments.push(Value::Bool(publish));
-
-        // 2. call rpc
-        let result = self.rpc_request(EDIT_POST, arguments)?;
-
-        // 3. parse result
-        Ok(result)
+        let supported = match self.call::<_, Vec<String>>(LIST_METHODS, &()) {
+            Ok(methods) => methods.iter().any(|method| method == MULTICALL),
+            Err(_) => false,
+        };
+        self.multicall.set(Some(supported));
+        supported
     }
 
-    /// Delete post by postid
-    pub fn delete_post(&self, postid: &str, publish: bool) -> Result<bool, Error> {
-        // 1. generate arguments
-        let mut arguments = Vec::<Value>::new();
-        arguments.push(Value::String(self.app_key.clone()));
-        arguments.push(Value::String(postid.to_string()));
-        arguments.push(Value::String(self.username.to_string()));
-        arguments.push(Value::String(self.password.clone()));
-        arguments.push(Value::Bool(publish));
+    /// call `method` for every parameters in one `system.multicall` request
+    fn multicall<P: Serialize, R: FromResponse>(&self, method: &str, params: &[P]) -> Result<Vec<Result<R, RpcError>>, RpcError> {
+        // 1. generate calls
+        let mut calls = Vec::with_capacity(params.len());
+        for params in params.iter() {
+            let params = to_params(params).map_err(|e| RpcError::Params {
+                method: method.to_string(),
+                message: e.to_string(),
+            })?;
+            calls.push(Value::Struct(BTreeMap::from([
+                ("methodName".to_string(), Value::String(method.to_string())),
+                ("params".to_string(), Value::Array(params)),
+            ])));
+        }
 
         // 2. call rpc
-        let result = self.rpc_request(DELETE_POST, arguments)?;
-
-        // 3. parse result
-        if let Value::Bool(v) = result {
-            return Ok(v);
-        }
-        Ok(false)
+        let responses = match self.rpc_request(MULTICALL, vec![Value::Array(calls)])? {
+            Value::Array(responses) if responses.len() == params.len() => responses,
+            _ => {
+                return Err(RpcError::Response {
+                    method: MULTICALL.to_string(),
+                    message: format!("expected {} results", params.len()),
+                })
+            }
+        };
+
+        // 3. parse results, a result is `[value]` or a fault struct
+        let results = responses
+            .into_iter()
+            .map(|response| match response {
+                Value::Array(mut values) if values.len() == 1 => R::from_response(values.pop().unwrap())
+                    .map_err(|message| RpcError::Response { method: method.to_string(), message }),
+                response => match Fault::from_value(&response) {
+                    Some(fault) => Err(RpcError::Fault { method: method.to_string(), fault }),
+                    None => Err(RpcError::Response {
+                        method: method.to_string(),
+                        message: format!("unexpected result of {}", MULTICALL),
+                    }),
+                },
+            })
+            .collect();
+        Ok(results)
     }
 
-    fn rpc_request(&self, method: &str, args: Vec<Value>) -> Result<Value, Error> {
+    fn rpc_request(&self, method: &str, args: Vec<Value>) -> Result<Value, RpcError> {
         // When `request` call `arg()`, owenership entry function. So we need rereceive
         let mut request = Request::new(method);
 
         for arg in args.into_iter() {
             request = request.arg(arg);
         }
-        let mut f = File::create("1.xml").unwrap();
-        
-        request.write_as_xml(&mut f).unwrap();
-        request.call_url(self.url.as_str())
+        let timed_out = Cell::new(None);
+        let transport = HttpTransport { builder: self.client.post(self.url.as_str()), timed_out: &timed_out };
+        request.call(transport).map_err(|e| match timed_out.get() {
+            Some(connect) => RpcError::Timeout {
+                method: method.to_string(),
+                connect,
+                timeout: if connect { self.connect_timeout } else { self.read_timeout },
+            },
+            None => RpcError::Call(e),
+        })
+    }
+
+    /// post as a parameter, created now if it has no date
+    fn post_value(mut post: Post) -> Value {
+        if post.date_created == DateTime::default() {
+            post.date_created = Self::get_now_time();
+        }
+        Value::from(post)
     }
 
     fn get_now_time() -> DateTime {
@@ -217,11 +421,112 @@ impl MetaWeblog {
     }
 }
 
+/// local xmlrpc server answering prepared responses, shared by tests
+#[cfg(test)]
+pub(crate) mod mock {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread::{self, JoinHandle};
+
+    use super::MetaWeblog;
+
+    /// answer requests in order by `responses`, one connection per request, return request bodies
+    pub fn serve(responses: Vec<String>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                requests.push(read_request(&mut reader));
+                write_response(reader.get_mut(), response.as_str(), true);
+            }
+            requests
+        });
+        (endpoint, handle)
+    }
+
+    /// body of a http request
+    pub fn read_request(reader: &mut BufReader<TcpStream>) -> String {
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+            if line.trim().is_empty() {
+                break;
+            }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        String::from_utf8(body).unwrap()
+    }
+
+    pub fn write_response(stream: &mut TcpStream, response: &str, close: bool) {
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/xml\r\nContent-Length: {}\r\nConnection: {}\r\n\r\n{}",
+            response.len(),
+            if close { "close" } else { "keep-alive" },
+            response
+        )
+        .unwrap();
+    }
+
+    pub fn response(value: &str) -> String {
+        format!(
+            "<?xml version=\"1.0\"?><methodResponse><params><param><value>{}</value></param></params></methodResponse>",
+            value
+        )
+    }
+
+    pub const FAULT: &str = "<struct><member><name>faultCode</name><value><int>1</int></value></member>\
+        <member><name>faultString</name><value><string>post not found</string></value></member></struct>";
+
+    pub fn post(title: &str) -> String {
+        format!("<struct><member><name>title</name><value><string>{}</string></value></member></struct>", title)
+    }
+
+    /// answer of a fault
+    pub fn fault() -> String {
+        format!("<?xml version=\"1.0\"?><methodResponse><fault><value>{}</value></fault></methodResponse>", FAULT)
+    }
+
+    /// post with title and content
+    pub fn post_content(title: &str, description: &str) -> String {
+        format!(
+            "<struct><member><name>title</name><value><string>{}</string></value></member>\
+            <member><name>description</name><value><string>{}</string></value></member></struct>",
+            title, description
+        )
+    }
+
+    pub fn local_weblog(endpoint: &str) -> MetaWeblog {
+        let mut weblog = MetaWeblog::new("user".to_string(), "password".to_string(), "1".to_string(), "app".to_string());
+        weblog.set_endpoint(endpoint);
+        weblog
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use chrono::{Datelike, Timelike};
 
-    use super::{WpCategory, MetaWeblog};
+    use std::io::BufReader;
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+
+    use xmlrpc::Value;
+
+    use super::mock::{fault, local_weblog, post, read_request, response, serve, write_response, FAULT};
+    use super::{FromResponse, MetaWeblog, RpcError, WpCategory};
+    use crate::Post;
 
     #[test]
     fn get_users_blogs() {
@@ -232,8 +537,10 @@ mod tests {
     #[test]
     fn new_category() {
         let weblog = MetaWeblog::new("上海的海是海未的海".to_string(), "63F4E40156E9BCE22EC53B951D1ED9D6D2855218E78DB9AE338B7FF63123BC0E".to_string(), "123".to_string(), "lunar-umi".to_string());
-        let mut category = WpCategory::default();
-        category.name = "Cates".to_string();
+        let category = WpCategory {
+            name: "Cates".to_string(),
+            ..Default::default()
+        };
         let a = weblog.new_category(category).unwrap();
         dbg!(a);
     }
@@ -267,7 +574,93 @@ mod tests {
         let now = chrono::Local::now();
         let mut post = crate::Post::default();
         let s = format!("{}-{:02}-{:02}T{:02}:{:02}:{:02}", now.year(), now.month(), now.day(), now.hour(), now.minute(), now.second());
-        post.dateCreated = iso8601::datetime(s.as_str()).unwrap();
+        post.date_created = iso8601::datetime(s.as_str()).unwrap();
         println!("{}", s);
     }
+
+    #[test]
+    fn typed_responses() {
+        assert_eq!(String::from_response(Value::String("1".to_string())).unwrap(), "1");
+        // unexpected shapes are errors instead of sentinel values
+        assert!(String::from_response(Value::Bool(false)).is_err());
+        assert!(i32::from_response(Value::String("-1".to_string())).is_err());
+
+        let posts = Value::Array(vec![Value::from(Post { title: "a".to_string(), ..Default::default() })]);
+        assert_eq!(Vec::<Post>::from_response(posts).unwrap()[0].title, "a");
+        let message = Vec::<Post>::from_response(Value::Nil).unwrap_err();
+        let error = RpcError::Response { method: super::GET_RECENT_POSTS.to_string(), message };
+        assert_eq!(error.to_string(), "unexpected response of metaWeblog.getRecentPosts: `[Post]`: expected array, found nil");
+        assert!(error.fault().is_none());
+    }
+
+    #[test]
+    fn multicall_batches() {
+        let methods = "<array><data><value><string>metaWeblog.getPost</string></value>\
+            <value><string>system.multicall</string></value></data></array>";
+        let results = format!(
+            "<array><data><value><array><data><value>{}</value></data></array></value><value>{}</value></data></array>",
+            post("a"),
+            FAULT
+        );
+        let (endpoint, server) = serve(vec![response(methods), response(results.as_str())]);
+        let weblog = local_weblog(endpoint.as_str());
+        let posts = weblog.get_posts(&["1".to_string(), "2".to_string()]);
+        assert_eq!(posts[0].as_ref().unwrap().title, "a");
+        assert_eq!(posts[1].as_ref().unwrap_err().fault().unwrap().fault_code, 1);
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains("system.listMethods"));
+        assert!(requests[1].contains("system.multicall") && requests[1].contains("metaWeblog.getPost"));
+    }
+
+    #[test]
+    fn multicall_fallback() {
+        let (endpoint, server) = serve(vec![fault(), response(post("a").as_str()), response(post("b").as_str())]);
+        let weblog = local_weblog(endpoint.as_str());
+        let posts = weblog.get_posts(&["1".to_string(), "2".to_string()]);
+        let titles: Vec<String> = posts.into_iter().map(|post| post.unwrap().title).collect();
+        assert_eq!(titles, ["a", "b"]);
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[1..].iter().all(|request| request.contains("metaWeblog.getPost")));
+    }
+
+    #[test]
+    fn reuse_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        // only one connection is accepted
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            for title in ["a", "b"] {
+                read_request(&mut reader);
+                write_response(reader.get_mut(), response(post(title).as_str()).as_str(), false);
+            }
+        });
+        let mut weblog = local_weblog(endpoint.as_str());
+        weblog.set_timeouts(Duration::from_secs(2), Duration::from_secs(2));
+        assert_eq!(weblog.get_post("1").unwrap().title, "a");
+        assert_eq!(weblog.get_post("2").unwrap().title, "b");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn read_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        // the request is never answered
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            thread::sleep(Duration::from_secs(2));
+            drop(stream);
+        });
+        let mut weblog = local_weblog(endpoint.as_str());
+        weblog.set_timeouts(Duration::from_secs(1), Duration::from_millis(500));
+        let error = weblog.get_post("1").unwrap_err();
+        assert!(matches!(error, RpcError::Timeout { connect: false, .. }), "{}", error);
+        assert!(error.to_string().contains("read_timeout"));
+        server.join().unwrap();
+    }
 }
@@ -0,0 +1,746 @@
+// serde bridge: any `Serialize`/`Deserialize` type from/to `xmlrpc::Value`
+use std::collections::{btree_map, BTreeMap};
+use std::fmt;
+use std::vec;
+
+use iso8601::{Date, DateTime};
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+use serde::ser::{self, Serialize};
+use xmlrpc::Value;
+
+/// name of the newtype struct carrying a `dateTime.iso8601`, see `datetime`
+const DATETIME: &str = "$xmlrpc::DateTime";
+
+/// error of serializing to or deserializing from `Value`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueError {
+    /// member path like `posts[1].title`, empty while the value itself is wrong
+    path: Vec<String>,
+    message: String,
+}
+
+impl ValueError {
+    fn new(message: String) -> ValueError {
+        ValueError { path: Vec::new(), message }
+    }
+
+    /// error of the value in `member`
+    fn within(mut self, member: String) -> ValueError {
+        self.path.insert(0, member);
+        self
+    }
+}
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() {
+            return write!(f, "{}", self.message);
+        }
+        let mut path = String::new();
+        for member in self.path.iter() {
+            if !path.is_empty() && !member.starts_with('[') {
+                path.push('.');
+            }
+            path.push_str(member);
+        }
+        write!(f, "`{}`: {}", path, self.message)
+    }
+}
+
+impl std::error::Error for ValueError {}
+
+impl ser::Error for ValueError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        ValueError::new(msg.to_string())
+    }
+}
+
+impl de::Error for ValueError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        ValueError::new(msg.to_string())
+    }
+}
+
+/// serialize to `Value`, struct fields of `None` are omitted rather than sent as nil
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value, ValueError> {
+    value.serialize(Serializer)
+}
+
+/// rpc parameters from a tuple (or a single value)
+pub fn to_params<T: Serialize + ?Sized>(params: &T) -> Result<Vec<Value>, ValueError> {
+    match to_value(params)? {
+        Value::Array(values) => Ok(values),
+        Value::Nil => Ok(Vec::new()),
+        value => Ok(vec![value]),
+    }
+}
+
+/// deserialize from `Value`
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, ValueError> {
+    T::deserialize(Deserializer(value))
+}
+
+/// `iso8601::DateTime` as `dateTime.iso8601`, use with `#[serde(with = "value::datetime")]`
+/// other formats (json, toml) see an iso8601 string
+// only referenced by `#[serde(with)]`, no rpc type needs it yet
+#[allow(dead_code)]
+pub mod datetime {
+    use iso8601::DateTime;
+    use serde::de::{self, Visitor};
+    use serde::{Deserializer, Serializer};
+    use std::fmt;
+
+    use super::{format_datetime, DATETIME};
+
+    pub fn serialize<S: Serializer>(datetime: &DateTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(DATETIME, &format_datetime(datetime))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime, D::Error> {
+        struct DateTimeVisitor;
+
+        impl<'de> Visitor<'de> for DateTimeVisitor {
+            type Value = DateTime;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an iso8601 datetime")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<DateTime, E> {
+                iso8601::datetime(v).map_err(|e| E::custom(format!("invalid datetime `{}`: {}", v, e)))
+            }
+
+            fn visit_newtype_struct<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<DateTime, D::Error> {
+                deserializer.deserialize_str(self)
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(DATETIME, DateTimeVisitor)
+    }
+}
+
+/// iso8601 string which `iso8601::datetime` parses back
+/// (Display of iso8601 0.4 writes the hours offset twice)
+fn format_datetime(datetime: &DateTime) -> String {
+    let date = match datetime.date {
+        Date::YMD { year, month, day } => format!("{:04}-{:02}-{:02}", year, month, day),
+        Date::Week { year, ww, d } => format!("{:04}-W{:02}-{}", year, ww, d),
+        Date::Ordinal { year, ddd } => format!("{:04}-{:03}", year, ddd),
+    };
+    let time = &datetime.time;
+    let mut s = format!("{}T{:02}:{:02}:{:02}", date, time.hour, time.minute, time.second);
+    if time.millisecond != 0 {
+        s.push_str(format!(".{:03}", time.millisecond).as_str());
+    }
+    if time.tz_offset_hours != 0 || time.tz_offset_minutes != 0 {
+        let sign = if time.tz_offset_hours < 0 || time.tz_offset_minutes < 0 { '-' } else { '+' };
+        s.push_str(format!("{}{:02}:{:02}", sign, time.tz_offset_hours.abs(), time.tz_offset_minutes.abs()).as_str());
+    }
+    s
+}
+
+/// serialize a `Value` as it is, e.g. a `Convert` struct in rpc parameters
+pub struct Raw<'a>(pub &'a Value);
+
+impl Serialize for Raw<'_> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use ser::{SerializeMap, SerializeSeq};
+
+        match self.0 {
+            Value::Int(v) => serializer.serialize_i32(*v),
+            Value::Int64(v) => serializer.serialize_i64(*v),
+            Value::Bool(v) => serializer.serialize_bool(*v),
+            Value::String(v) => serializer.serialize_str(v),
+            Value::Double(v) => serializer.serialize_f64(*v),
+            Value::DateTime(v) => serializer.serialize_newtype_struct(DATETIME, &format_datetime(v)),
+            Value::Base64(v) => serializer.serialize_bytes(v),
+            Value::Struct(members) => {
+                let mut map = serializer.serialize_map(Some(members.len()))?;
+                for (k, v) in members.iter() {
+                    map.serialize_entry(k, &Raw(v))?;
+                }
+                map.end()
+            }
+            Value::Array(values) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for v in values.iter() {
+                    seq.serialize_element(&Raw(v))?;
+                }
+                seq.end()
+            }
+            Value::Nil => serializer.serialize_unit(),
+        }
+    }
+}
+
+/// `{ variant: value }` of an enum variant with data
+fn variant(variant: &'static str, value: Value) -> Value {
+    Value::Struct(BTreeMap::from([(variant.to_string(), value)]))
+}
+
+struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = Value;
+    type Error = ValueError;
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeArray;
+    type SerializeMap = SerializeMembers;
+    type SerializeStruct = SerializeMembers;
+    type SerializeStructVariant = SerializeMembers;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, ValueError> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, ValueError> {
+        Ok(Value::Int(v as i32))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, ValueError> {
+        Ok(Value::Int(v as i32))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, ValueError> {
+        Ok(Value::Int(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, ValueError> {
+        Ok(Value::Int64(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, ValueError> {
+        Ok(Value::Int(v as i32))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, ValueError> {
+        Ok(Value::Int(v as i32))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, ValueError> {
+        Ok(i32::try_from(v).map(Value::Int).unwrap_or(Value::Int64(v as i64)))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, ValueError> {
+        i64::try_from(v)
+            .map(Value::Int64)
+            .map_err(|_| ValueError::new(format!("{} is out of range of i64", v)))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, ValueError> {
+        Ok(Value::Double(v as f64))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, ValueError> {
+        Ok(Value::Double(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, ValueError> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, ValueError> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, ValueError> {
+        Ok(Value::Base64(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<Value, ValueError> {
+        Ok(Value::Nil)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, ValueError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, ValueError> {
+        Ok(Value::Nil)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, ValueError> {
+        Ok(Value::Nil)
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<Value, ValueError> {
+        Ok(Value::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, name: &'static str, value: &T) -> Result<Value, ValueError> {
+        if name != DATETIME {
+            return value.serialize(self);
+        }
+        match value.serialize(self)? {
+            Value::String(v) => iso8601::datetime(v.as_str())
+                .map(Value::DateTime)
+                .map_err(|e| ValueError::new(format!("invalid datetime `{}`: {}", v, e))),
+            _ => Err(ValueError::new("datetime must be a string".to_string())),
+        }
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Value, ValueError> {
+        Ok(variant(name, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeArray, ValueError> {
+        Ok(SerializeArray { values: Vec::with_capacity(len.unwrap_or(0)), variant: None })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeArray, ValueError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SerializeArray, ValueError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeArray, ValueError> {
+        Ok(SerializeArray { values: Vec::with_capacity(len), variant: Some(variant) })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMembers, ValueError> {
+        Ok(SerializeMembers { members: BTreeMap::new(), key: None, variant: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<SerializeMembers, ValueError> {
+        self.serialize_map(None)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<SerializeMembers, ValueError> {
+        Ok(SerializeMembers { members: BTreeMap::new(), key: None, variant: Some(variant) })
+    }
+}
+
+struct SerializeArray {
+    values: Vec<Value>,
+    variant: Option<&'static str>,
+}
+
+impl SerializeArray {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        let i = self.values.len();
+        self.values.push(to_value(value).map_err(|e| e.within(format!("[{}]", i)))?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, ValueError> {
+        let array = Value::Array(self.values);
+        Ok(match self.variant {
+            Some(name) => variant(name, array),
+            None => array,
+        })
+    }
+}
+
+impl ser::SerializeSeq for SerializeArray {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SerializeArray {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeArray {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        self.finish()
+    }
+}
+
+struct SerializeMembers {
+    members: BTreeMap<String, Value>,
+    /// key of map entry whose value isn't serialized yet
+    key: Option<String>,
+    variant: Option<&'static str>,
+}
+
+impl SerializeMembers {
+    fn insert<T: Serialize + ?Sized>(&mut self, key: String, value: &T, omit_nil: bool) -> Result<(), ValueError> {
+        let value = to_value(value).map_err(|e| e.within(key.clone()))?;
+        if !(omit_nil && value == Value::Nil) {
+            self.members.insert(key, value);
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, ValueError> {
+        let members = Value::Struct(self.members);
+        Ok(match self.variant {
+            Some(name) => variant(name, members),
+            None => members,
+        })
+    }
+}
+
+impl ser::SerializeMap for SerializeMembers {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), ValueError> {
+        // member names of xmlrpc struct are strings
+        self.key = Some(match to_value(key)? {
+            Value::String(key) => key,
+            Value::Int(key) => key.to_string(),
+            Value::Int64(key) => key.to_string(),
+            _ => return Err(ValueError::new("key of struct must be a string".to_string())),
+        });
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        let key = self.key.take().expect("serialize_value is called before serialize_key");
+        self.insert(key, value, false)
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for SerializeMembers {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), ValueError> {
+        self.insert(key.to_string(), value, true)
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeMembers {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), ValueError> {
+        self.insert(key.to_string(), value, true)
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        self.finish()
+    }
+}
+
+struct Deserializer(Value);
+
+impl<'de> de::Deserializer<'de> for Deserializer {
+    type Error = ValueError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        match self.0 {
+            Value::Int(v) => visitor.visit_i32(v),
+            Value::Int64(v) => visitor.visit_i64(v),
+            Value::Bool(v) => visitor.visit_bool(v),
+            Value::String(v) => visitor.visit_string(v),
+            Value::Double(v) => visitor.visit_f64(v),
+            Value::DateTime(v) => visitor.visit_string(format_datetime(&v)),
+            Value::Base64(v) => visitor.visit_byte_buf(v),
+            Value::Struct(members) => visitor.visit_map(MembersAccess { members: members.into_iter(), value: None }),
+            Value::Array(values) => visitor.visit_seq(ArrayAccess { values: values.into_iter().enumerate() }),
+            Value::Nil => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        match self.0 {
+            Value::Nil => visitor.visit_none(),
+            value => visitor.visit_some(Deserializer(value)),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, name: &'static str, visitor: V) -> Result<V::Value, ValueError> {
+        if name != DATETIME {
+            return visitor.visit_newtype_struct(self);
+        }
+        match self.0 {
+            Value::DateTime(v) => visitor.visit_string(format_datetime(&v)),
+            Value::String(v) => visitor.visit_string(v),
+            value => Err(de::Error::invalid_type(unexpected(&value), &visitor)),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        match self.0 {
+            Value::String(v) => visitor.visit_enum(v.into_deserializer()),
+            Value::Struct(members) if members.len() == 1 => {
+                let (name, value) = members.into_iter().next().unwrap();
+                visitor.visit_enum(VariantAccess { name, value })
+            }
+            value => Err(de::Error::invalid_type(unexpected(&value), &visitor)),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+/// `Unexpected` of a value for type errors
+fn unexpected(value: &Value) -> de::Unexpected<'_> {
+    match value {
+        Value::Int(v) => de::Unexpected::Signed(*v as i64),
+        Value::Int64(v) => de::Unexpected::Signed(*v),
+        Value::Bool(v) => de::Unexpected::Bool(*v),
+        Value::String(v) => de::Unexpected::Str(v),
+        Value::Double(v) => de::Unexpected::Float(*v),
+        Value::DateTime(_) => de::Unexpected::Other("datetime"),
+        Value::Base64(v) => de::Unexpected::Bytes(v),
+        Value::Struct(_) => de::Unexpected::Map,
+        Value::Array(_) => de::Unexpected::Seq,
+        Value::Nil => de::Unexpected::Unit,
+    }
+}
+
+struct MembersAccess {
+    members: btree_map::IntoIter<String, Value>,
+    /// member whose key is deserialized
+    value: Option<(String, Value)>,
+}
+
+impl<'de> de::MapAccess<'de> for MembersAccess {
+    type Error = ValueError;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, ValueError> {
+        match self.members.next() {
+            Some((k, v)) => {
+                let key = seed.deserialize(k.as_str().into_deserializer())?;
+                self.value = Some((k, v));
+                Ok(Some(key))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<T: de::DeserializeSeed<'de>>(&mut self, seed: T) -> Result<T::Value, ValueError> {
+        let (k, v) = self.value.take().expect("next_value_seed is called before next_key_seed");
+        seed.deserialize(Deserializer(v)).map_err(|e| e.within(k))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.members.len())
+    }
+}
+
+struct ArrayAccess {
+    values: std::iter::Enumerate<vec::IntoIter<Value>>,
+}
+
+impl<'de> de::SeqAccess<'de> for ArrayAccess {
+    type Error = ValueError;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, ValueError> {
+        match self.values.next() {
+            Some((i, v)) => seed
+                .deserialize(Deserializer(v))
+                .map(Some)
+                .map_err(|e| e.within(format!("[{}]", i))),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len())
+    }
+}
+
+/// enum variant with data, `{ variant: value }`
+struct VariantAccess {
+    name: String,
+    value: Value,
+}
+
+impl<'de> de::EnumAccess<'de> for VariantAccess {
+    type Error = ValueError;
+    type Variant = Deserializer;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Deserializer), ValueError> {
+        let variant = seed.deserialize(self.name.as_str().into_deserializer())?;
+        Ok((variant, Deserializer(self.value)))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Deserializer {
+    type Error = ValueError;
+
+    fn unit_variant(self) -> Result<(), ValueError> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, ValueError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, ValueError> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, ValueError> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::meta_weblog::cfg::UserInfo;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Post {
+        title: String,
+        #[serde(with = "datetime")]
+        date_created: DateTime,
+        categories: Vec<String>,
+        mt_keywords: Option<String>,
+        state: State,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum State {
+        Published,
+        Draft { reason: String },
+    }
+
+    #[test]
+    fn round_trip() {
+        let post = Post {
+            title: "title".to_string(),
+            date_created: iso8601::datetime("2022-05-01T10:20:30+08:00").unwrap(),
+            categories: vec!["[Markdown]".to_string()],
+            mt_keywords: None,
+            state: State::Published,
+        };
+        let value = to_value(&post).unwrap();
+        if let Value::Struct(ref members) = value {
+            let names: Vec<&str> = members.keys().map(|name| name.as_str()).collect();
+            assert_eq!(names, ["categories", "dateCreated", "state", "title"]);
+            assert_eq!(members.get("dateCreated"), Some(&Value::DateTime(post.date_created)));
+            assert_eq!(members.get("state"), Some(&Value::String("published".to_string())));
+        } else {
+            panic!("struct is expected");
+        }
+        assert_eq!(from_value::<Post>(value).unwrap(), post);
+
+        let draft = State::Draft { reason: "wip".to_string() };
+        assert_eq!(from_value::<State>(to_value(&draft).unwrap()).unwrap(), draft);
+        // json sees a string
+        let json = serde_json::to_string(&post).unwrap();
+        assert!(json.contains("\"dateCreated\":\"2022-05-01T10:20:30+08:00\""));
+        assert_eq!(serde_json::from_str::<Post>(json.as_str()).unwrap(), post);
+    }
+
+    #[test]
+    fn rpc_params() {
+        let user_info = UserInfo {
+            username: "user".to_string(),
+            password: "password".to_string(),
+            app_key: "app".to_string(),
+            blogid: "1".to_string(),
+            postid: 2,
+        };
+        let value = to_value(&user_info).unwrap();
+        if let Value::Struct(ref members) = value {
+            // password is skipped
+            assert_eq!(members.len(), 4);
+            assert_eq!(members.get("postid"), Some(&Value::Int(2)));
+        } else {
+            panic!("struct is expected");
+        }
+        let user_info: UserInfo = from_value(value).unwrap();
+        assert_eq!(user_info.blogid, "1");
+        assert_eq!(user_info.password, "");
+
+        let raw = Value::Struct(BTreeMap::from([("bits".to_string(), Value::Base64(vec![1, 2]))]));
+        let params = to_params(&("1", 10u32, true, Raw(&raw))).unwrap();
+        assert_eq!(params, [Value::String("1".to_string()), Value::Int(10), Value::Bool(true), raw]);
+    }
+
+    #[test]
+    fn error_paths() {
+        let value = Value::Struct(BTreeMap::from([
+            ("title".to_string(), Value::String("title".to_string())),
+            ("dateCreated".to_string(), Value::String("2022-05-01T10:20:30".to_string())),
+            ("categories".to_string(), Value::Array(vec![Value::String("a".to_string()), Value::Int(1)])),
+            ("state".to_string(), Value::String("published".to_string())),
+        ]));
+        let error = from_value::<Post>(value).unwrap_err();
+        assert_eq!(error.to_string(), "`categories[1]`: invalid type: integer `1`, expected a string");
+        let error = from_value::<Post>(Value::Int(1)).unwrap_err();
+        assert_eq!(error.to_string(), "invalid type: integer `1`, expected struct Post");
+        let error = from_value::<State>(Value::String("deleted".to_string())).unwrap_err();
+        assert!(error.to_string().starts_with("unknown variant `deleted`"));
+    }
+}
@@ -1,11 +1,110 @@
 use cnblog::Convert;
 
 use std::collections::BTreeMap;
+use std::fmt;
 
 use iso8601::DateTime;
 use xmlrpc::Value;
 
-#[allow(non_snake_case)]
+/// error of converting `Value` to a `Convert` struct
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvertError {
+    /// name of struct
+    pub target: String,
+    /// member path like `categories[1]`, None while the value itself is wrong
+    pub member: Option<String>,
+    pub kind: ConvertErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConvertErrorKind {
+    /// xmlrpc type isn't expected
+    Mismatch { expected: &'static str, found: &'static str },
+    /// value can't be represented by rust type
+    Invalid(String),
+    /// required member is missing (strict mode)
+    Missing,
+    /// member isn't a field (strict mode)
+    Unknown,
+}
+
+impl ConvertError {
+    pub fn mismatch(target: &str, member: Option<String>, expected: &'static str, found: &Value) -> ConvertError {
+        ConvertError {
+            target: target.to_string(),
+            member,
+            kind: ConvertErrorKind::Mismatch { expected, found: ConvertError::type_name(found) },
+        }
+    }
+
+    pub fn invalid(target: &str, member: Option<String>, message: String) -> ConvertError {
+        ConvertError { target: target.to_string(), member, kind: ConvertErrorKind::Invalid(message) }
+    }
+
+    pub fn missing(target: &str, member: String) -> ConvertError {
+        ConvertError { target: target.to_string(), member: Some(member), kind: ConvertErrorKind::Missing }
+    }
+
+    pub fn unknown(target: &str, member: String) -> ConvertError {
+        ConvertError { target: target.to_string(), member: Some(member), kind: ConvertErrorKind::Unknown }
+    }
+
+    /// error of a nested struct in `member` of `target`
+    pub fn within(self, target: &str, member: String) -> ConvertError {
+        let member = match self.member {
+            Some(inner) => format!("{}.{}", member, inner),
+            None => member,
+        };
+        ConvertError { target: target.to_string(), member: Some(member), kind: self.kind }
+    }
+
+    /// xmlrpc type name of value
+    pub fn type_name(value: &Value) -> &'static str {
+        match value {
+            Value::Int(_) => "int",
+            Value::Int64(_) => "i8",
+            Value::Bool(_) => "boolean",
+            Value::String(_) => "string",
+            Value::Double(_) => "double",
+            Value::DateTime(_) => "dateTime.iso8601",
+            Value::Base64(_) => "base64",
+            Value::Struct(_) => "struct",
+            Value::Array(_) => "array",
+            Value::Nil => "nil",
+        }
+    }
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.member.as_ref() {
+            Some(member) => write!(f, "`{}.{}`: ", self.target, member)?,
+            None => write!(f, "`{}`: ", self.target)?,
+        }
+        match &self.kind {
+            ConvertErrorKind::Mismatch { expected, found } => write!(f, "expected {}, found {}", expected, found),
+            ConvertErrorKind::Invalid(message) => write!(f, "{}", message),
+            ConvertErrorKind::Missing => write!(f, "missing member"),
+            ConvertErrorKind::Unknown => write!(f, "unknown member"),
+        }
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+/// convert array of `Convert` structs, like the response of `getRecentPosts`
+pub fn try_from_array<T: TryFrom<Value, Error = ConvertError>>(value: Value) -> Result<Vec<T>, ConvertError> {
+    let target = std::any::type_name::<T>().rsplit("::").next().unwrap();
+    match value {
+        Value::Array(values) => values
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| T::try_from(v).map_err(|e| e.within(format!("[{}]", target).as_str(), i.to_string())))
+            .collect(),
+        value => Err(ConvertError::mismatch(format!("[{}]", target).as_str(), None, "array", &value)),
+    }
+}
+
 #[derive(Default, Debug, Convert)]
 pub struct UserBlogs {
     app_key: String,
@@ -14,12 +113,12 @@ pub struct UserBlogs {
 }
 
 // BlogInfo
-#[allow(non_snake_case)]
 #[derive(Default, Debug, Convert)]
+#[convert(rename_all = "camelCase")]
 pub struct BlogInfo {
     pub blogid: String,
     pub url: String,
-    pub blogName: String,
+    pub blog_name: String,
 }
 
 impl Post {
@@ -29,7 +128,7 @@ impl Post {
             for (k, v) in a.into_iter() {
                 if k == "dateCreated" {
                     if let Value::DateTime(v) = v {
-                        self.dateCreated = v;
+                        self.date_created = v;
                         continue;
                     }
                     if let Value::Array(v) = v {
@@ -48,23 +147,26 @@ impl Post {
 }
 
 // Post
-#[allow(non_snake_case)]
 #[derive(Debug, Default, Clone, Convert)]
+#[convert(rename_all = "camelCase")]
 pub struct Post {
     pub postid: String,
-    pub dateCreated: DateTime,
+    pub date_created: DateTime,
     pub description: String,
     pub title: String,
     pub categories: Vec<String>,
+    /// other members of remote post, they're kept while editing it
+    #[convert(flatten)]
+    pub extra: BTreeMap<String, Value>,
 }
 
 // CategoryInfo
-#[allow(non_snake_case)]
 #[derive(Debug, Default, Convert)]
+#[convert(rename_all = "camelCase")]
 pub struct CategoryInfo {
     pub description: String,
-    pub htmlUrl: String,
-    pub rssUrl: String,
+    pub html_url: String,
+    pub rss_url: String,
     pub title: String,
     pub categoryid: String,
 }
@@ -75,3 +177,234 @@ pub struct WpCategory {
     pub name: String,
     pub parent_id: i32,
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveDateTime};
+
+    use super::*;
+
+    #[derive(Debug, Default, PartialEq, Convert)]
+    struct Members {
+        int: i32,
+        int64: i64,
+        flag: bool,
+        double: f64,
+        text: String,
+        date: DateTime,
+        naive: Option<NaiveDateTime>,
+        bytes: Vec<u8>,
+        tags: Vec<String>,
+        scores: BTreeMap<String, i32>,
+        note: Option<String>,
+        raw: Option<Value>,
+    }
+
+    fn default_count() -> i32 {
+        10
+    }
+
+    #[derive(Debug, Default, Convert)]
+    #[convert(rename_all = "camelCase")]
+    struct Renamed {
+        blog_name: String,
+        #[convert(rename = "mt_keywords")]
+        keywords: String,
+        #[convert(skip)]
+        local_only: String,
+        #[convert(default = "default_count")]
+        post_count: i32,
+    }
+
+    #[test]
+    fn convert_every_type() {
+        let members = Members {
+            int: 1,
+            int64: 1 << 40,
+            flag: true,
+            double: 0.5,
+            text: "text".to_string(),
+            date: iso8601::datetime("2022-05-01T10:20:30").unwrap(),
+            naive: Some(NaiveDate::from_ymd_opt(2022, 5, 1).unwrap().and_hms_opt(10, 20, 30).unwrap()),
+            bytes: vec![0, 1, 2],
+            tags: vec!["a".to_string(), "b".to_string()],
+            scores: BTreeMap::from([("a".to_string(), 1)]),
+            note: None,
+            raw: Some(Value::Int(3)),
+        };
+        let value = Value::from(members);
+        if let Value::Struct(ref members) = value {
+            assert_eq!(members.get("bytes"), Some(&Value::Base64(vec![0, 1, 2])));
+            assert_eq!(members.get("naive"), members.get("date"));
+            // None is omitted
+            assert!(!members.contains_key("note"));
+        } else {
+            panic!("struct is expected");
+        }
+        let members = Members::try_from(value).unwrap();
+        assert_eq!(members.int64, 1 << 40);
+        assert_eq!(members.naive, Some(NaiveDate::from_ymd_opt(2022, 5, 1).unwrap().and_hms_opt(10, 20, 30).unwrap()));
+        assert_eq!(members.scores.get("a"), Some(&1));
+        assert_eq!(members.raw, Some(Value::Int(3)));
+    }
+
+    #[test]
+    fn field_attributes() {
+        let renamed = Renamed {
+            blog_name: "blog".to_string(),
+            keywords: "rust".to_string(),
+            local_only: "local".to_string(),
+            post_count: 1,
+        };
+        let value = Value::from(renamed);
+        if let Value::Struct(ref members) = value {
+            let names: Vec<&str> = members.keys().map(|name| name.as_str()).collect();
+            assert_eq!(names, ["blogName", "mt_keywords", "postCount"]);
+        } else {
+            panic!("struct is expected");
+        }
+
+        let renamed = Renamed::try_from(Value::Struct(BTreeMap::from([
+            ("blogName".to_string(), Value::String("blog".to_string())),
+            ("local_only".to_string(), Value::String("remote".to_string())),
+        ])))
+        .unwrap();
+        assert_eq!(renamed.blog_name, "blog");
+        assert_eq!(renamed.local_only, "");
+        assert_eq!(renamed.post_count, 10);
+    }
+
+    #[derive(Debug, Default, Convert)]
+    #[convert(strict)]
+    struct Strict {
+        title: String,
+        note: Option<String>,
+        #[convert(default = "default_count")]
+        count: i32,
+    }
+
+    #[test]
+    fn convert_errors() {
+        let error = Members::try_from(Value::Struct(BTreeMap::from([(
+            "tags".to_string(),
+            Value::Array(vec![Value::String("a".to_string()), Value::Int(1)]),
+        )])))
+        .unwrap_err();
+        assert_eq!(error.to_string(), "`Members.tags[1]`: expected string, found int");
+        let error = Members::try_from(Value::Array(Vec::new())).unwrap_err();
+        assert_eq!(error.to_string(), "`Members`: expected struct, found array");
+
+        let title = || ("title".to_string(), Value::String("title".to_string()));
+        assert!(Strict::try_from(Value::Struct(BTreeMap::from([title()]))).is_ok());
+        let error = Strict::try_from(Value::Struct(BTreeMap::new())).unwrap_err();
+        assert_eq!(error.kind, ConvertErrorKind::Missing);
+        let error = Strict::try_from(Value::Struct(BTreeMap::from([title(), ("other".to_string(), Value::Nil)])))
+            .unwrap_err();
+        assert_eq!(error.to_string(), "`Strict.other`: unknown member");
+    }
+
+    #[derive(Debug, Default, Convert)]
+    struct Blog {
+        name: String,
+        latest: Post,
+        posts: Vec<Post>,
+        owner: Option<Renamed>,
+    }
+
+    #[test]
+    fn nested_structs() {
+        let post = |title: &str| Post {
+            title: title.to_string(),
+            ..Default::default()
+        };
+        let blog = Blog {
+            name: "blog".to_string(),
+            latest: post("b"),
+            posts: vec![post("a"), post("b")],
+            owner: None,
+        };
+        let blog = Blog::try_from(Value::from(blog)).unwrap();
+        assert_eq!(blog.latest.title, "b");
+        assert_eq!(blog.posts.len(), 2);
+
+        let value = Value::Struct(BTreeMap::from([(
+            "posts".to_string(),
+            Value::Array(vec![Value::Struct(BTreeMap::from([("title".to_string(), Value::Int(1))]))]),
+        )]));
+        let error = Blog::try_from(value).unwrap_err();
+        assert_eq!(error.to_string(), "`Blog.posts[0].title`: expected string, found int");
+
+        let posts: Vec<Post> = try_from_array(Value::Array(vec![Value::from(post("a"))])).unwrap();
+        assert_eq!(posts[0].title, "a");
+        assert!(try_from_array::<Post>(Value::Nil).is_err());
+    }
+
+    #[test]
+    fn keep_unknown_members() {
+        let value = Value::Struct(BTreeMap::from([
+            ("title".to_string(), Value::String("title".to_string())),
+            ("permalink".to_string(), Value::String("https://example.com".to_string())),
+        ]));
+        let post = Post::try_from(value.clone()).unwrap();
+        assert_eq!(post.extra.len(), 1);
+        if let Value::Struct(members) = Value::from(post) {
+            assert_eq!(members.get("permalink"), Some(&Value::String("https://example.com".to_string())));
+        } else {
+            panic!("struct is expected");
+        }
+    }
+
+    #[test]
+    fn tolerate_missing_members() {
+        let value = Value::Struct(BTreeMap::from([
+            ("int64".to_string(), Value::Int(7)),
+            ("note".to_string(), Value::String("note".to_string())),
+        ]));
+        let members = Members::try_from(value).unwrap();
+        assert_eq!(members.int64, 7);
+        assert_eq!(members.note.as_deref(), Some("note"));
+        assert_eq!(members.naive, None);
+        assert!(members.tags.is_empty());
+    }
+
+    #[derive(Debug, Default, PartialEq, Convert)]
+    struct PostId(String);
+
+    #[derive(Debug, Default, PartialEq, Convert)]
+    struct Pair(i32, String);
+
+    #[derive(Debug, PartialEq, Convert)]
+    #[convert(rename_all = "lowercase")]
+    enum PostState {
+        Published,
+        #[convert(rename = "draft")]
+        Unpublished,
+    }
+
+    #[test]
+    fn tuple_structs() {
+        assert_eq!(Value::from(PostId("1".to_string())), Value::String("1".to_string()));
+        assert_eq!(PostId::try_from(Value::String("1".to_string())).unwrap(), PostId("1".to_string()));
+        let error = PostId::try_from(Value::Int(1)).unwrap_err();
+        assert_eq!(error.to_string(), "`PostId.0`: expected string, found int");
+
+        let pair = Value::from(Pair(1, "a".to_string()));
+        assert_eq!(pair, Value::Array(vec![Value::Int(1), Value::String("a".to_string())]));
+        assert_eq!(Pair::try_from(pair).unwrap(), Pair(1, "a".to_string()));
+        let error = Pair::try_from(Value::Array(vec![Value::Int(1)])).unwrap_err();
+        assert_eq!(error.to_string(), "`Pair`: expected 2 elements, found 1");
+    }
+
+    #[test]
+    fn unit_enums() {
+        assert_eq!(Value::from(PostState::Published), Value::String("published".to_string()));
+        assert_eq!(Value::from(PostState::Unpublished), Value::String("draft".to_string()));
+        assert_eq!(PostState::try_from(Value::String("draft".to_string())).unwrap(), PostState::Unpublished);
+        let error = PostState::try_from(Value::String("deleted".to_string())).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "`PostState`: unknown variant `deleted`, expected one of `published`, `draft`"
+        );
+        assert!(PostState::try_from(Value::Int(1)).is_err());
+    }
+}
@@ -0,0 +1,103 @@
+// named profiles, every profile has its own user info, blogs info and default root path
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::meta_weblog::cfg::USER_INFO_CFG;
+
+/// directory of named profiles in config directory
+const PROFILES_DIR: &str = "profiles";
+/// profile saved in config directory itself, it's the only profile of older cnblog
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// get config directory of profile
+pub fn profile_path(config: &Path, profile: &str) -> PathBuf {
+    if profile == DEFAULT_PROFILE {
+        config.to_path_buf()
+    } else {
+        config.join(PROFILES_DIR).join(profile)
+    }
+}
+
+/// profile name is used as directory name, so only simple names are allowed
+pub fn check_name(profile: &str) -> Result<(), String> {
+    if profile.is_empty()
+        || !profile
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "invalid profile name `{}`, only letters, digits, `-` and `_` are allowed",
+            profile
+        ));
+    }
+    Ok(())
+}
+
+/// whether profile has been initialized
+pub fn exists(config: &Path, profile: &str) -> bool {
+    profile_path(config, profile).join(USER_INFO_CFG).exists()
+}
+
+/// list initialized profiles, default profile is the first
+pub fn list(config: &Path) -> Vec<String> {
+    let mut profiles = Vec::new();
+    if exists(config, DEFAULT_PROFILE) {
+        profiles.push(DEFAULT_PROFILE.to_string());
+    }
+    if let Ok(entries) = fs::read_dir(config.join(PROFILES_DIR)) {
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().to_str().map(|name| name.to_string()))
+            .filter(|name| check_name(name).is_ok() && exists(config, name))
+            .collect();
+        names.sort();
+        profiles.extend(names);
+    }
+    profiles
+}
+
+/// remove local config of profile, posts on cnblog are kept
+pub fn remove(config: &Path, profile: &str) -> io::Result<()> {
+    if profile == DEFAULT_PROFILE {
+        // other profiles are saved in the default one
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "default profile can't be removed",
+        ));
+    }
+    fs::remove_dir_all(profile_path(config, profile))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_names() {
+        assert!(check_name("team_1").is_ok());
+        assert!(check_name("").is_err());
+        assert!(check_name("../team").is_err());
+        assert!(check_name("a b").is_err());
+    }
+
+    #[test]
+    fn list_profiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path();
+        assert!(list(config).is_empty());
+
+        fs::write(config.join(USER_INFO_CFG), "{}").unwrap();
+        for profile in ["team", "home"] {
+            fs::create_dir_all(profile_path(config, profile)).unwrap();
+            fs::write(profile_path(config, profile).join(USER_INFO_CFG), "{}").unwrap();
+        }
+        // not initialized
+        fs::create_dir_all(profile_path(config, "empty")).unwrap();
+        assert_eq!(list(config), ["default", "home", "team"]);
+
+        remove(config, "team").unwrap();
+        assert!(remove(config, DEFAULT_PROFILE).is_err());
+        assert_eq!(list(config), ["default", "home"]);
+    }
+}
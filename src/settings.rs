@@ -0,0 +1,253 @@
+// settings of a profile saved in `settings.toml`
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+
+pub const SETTINGS_CFG: &str = "settings.toml";
+
+/// what happens to the remote post of a removed local blog
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DeletePolicy {
+    /// delete remote post with its comments
+    Delete,
+    /// keep remote post as a draft
+    Unpublish,
+    /// save a final copy of remote post into trash, then delete it
+    Archive,
+}
+
+impl DeletePolicy {
+    /// state recorded in blogs info
+    pub fn state(&self) -> &'static str {
+        match self {
+            DeletePolicy::Delete => "deleted",
+            DeletePolicy::Unpublish => "unpublished",
+            DeletePolicy::Archive => "archived",
+        }
+    }
+
+    /// what is done to remote post
+    pub fn action(&self) -> &'static str {
+        match self {
+            DeletePolicy::Delete => "delete",
+            DeletePolicy::Unpublish => "unpublish",
+            DeletePolicy::Archive => "archive",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    /// absolute root path of articles, it's pinned while initializing
+    pub root_path: Option<String>,
+    /// glob patterns of blog paths (relative to root path) which are never synced
+    pub ignore: Vec<String>,
+    /// publish uploaded posts, otherwise they're saved as drafts
+    pub publish: bool,
+    /// metaweblog endpoint, `<endpoint>/<app_key>` is requested
+    pub endpoint: Option<String>,
+    /// git-credential style command saving password, otherwise it's saved in an obfuscated file
+    pub credential_helper: Option<String>,
+    /// max number of remote blogs deleted by one sync
+    pub max_delete_count: usize,
+    /// max percentage of managed blogs deleted by one sync, a single blog is always allowed
+    pub max_delete_percent: usize,
+    /// what happens to the remote post of a removed local blog
+    pub delete_policy: DeletePolicy,
+    /// seconds to wait for connecting to the endpoint
+    pub connect_timeout: u64,
+    /// seconds to wait for a whole response
+    pub read_timeout: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            root_path: None,
+            ignore: Vec::new(),
+            publish: true,
+            endpoint: None,
+            credential_helper: None,
+            max_delete_count: 10,
+            max_delete_percent: 30,
+            delete_policy: DeletePolicy::Delete,
+            connect_timeout: 10,
+            read_timeout: 60,
+        }
+    }
+}
+
+impl Settings {
+    /// read settings of profile, default settings are used while the file doesn't exist
+    pub fn load(base_path: &Path) -> Result<Settings, String> {
+        let path = base_path.join(SETTINGS_CFG);
+        if !path.exists() {
+            return Ok(Settings::default());
+        }
+        let content = fs::read_to_string(path.as_path()).map_err(|e| format!("can't read {:?}: {}", path, e))?;
+        let settings: Settings = toml::from_str(content.as_str()).map_err(|e| format!("can't parse {:?}: {}", path, e))?;
+        for pattern in settings.ignore.iter() {
+            Pattern::new(pattern).map_err(|e| format!("invalid ignore rule `{}` in {:?}: {}", pattern, path, e))?;
+        }
+        Ok(settings)
+    }
+
+    /// write settings of profile
+    pub fn save(&self, base_path: &Path) {
+        let content = toml::to_string(self).unwrap();
+        fs::write(base_path.join(SETTINGS_CFG), content).expect("Unable to write settings file");
+    }
+
+    /// pin root path, it's saved as an absolute path
+    pub fn pin_root_path(&mut self, root_path: &str) {
+        self.root_path = Some(absolute_path(root_path).to_str().unwrap().to_string());
+    }
+
+    /// Check the root path given by command line against the pinned one
+    /// Syncing a wrong root path looks like deleting every blog, so any mismatch is an error
+    pub fn check_root_path(&self, root_path: Option<&str>) -> Result<String, String> {
+        let pinned = match self.root_path.as_ref() {
+            Some(pinned) => pinned,
+            None => {
+                return Err("root path isn't configured, pass it once by `--rootpath` to pin it".to_string());
+            }
+        };
+        let pinned_path = Path::new(pinned.as_str());
+        if !pinned_path.is_dir() {
+            return Err(format!(
+                "configured root path {} doesn't exist, create it or change `root_path` in {}",
+                pinned, SETTINGS_CFG
+            ));
+        }
+        if let Some(root_path) = root_path {
+            let given = absolute_path(root_path);
+            if !given.is_dir() || !same_path(given.as_path(), pinned_path) {
+                return Err(format!(
+                    "root path {} doesn't match configured root path {}, change `root_path` in {} to switch it",
+                    given.display(),
+                    pinned,
+                    SETTINGS_CFG
+                ));
+            }
+        }
+        Ok(pinned.clone())
+    }
+
+    /// refuse to delete `deleted` of `managed` blogs if it's more than limits
+    pub fn check_mass_delete(&self, deleted: usize, managed: usize) -> Result<(), String> {
+        if deleted > self.max_delete_count {
+            return Err(format!(
+                "{} remote blogs would be deleted, more than `max_delete_count` ({}). Check root path, or pass `--allow-mass-delete`",
+                deleted, self.max_delete_count
+            ));
+        }
+        if deleted > 1 && deleted * 100 > managed * self.max_delete_percent {
+            return Err(format!(
+                "{} of {} remote blogs would be deleted, more than `max_delete_percent` ({}%). Check root path, or pass `--allow-mass-delete`",
+                deleted, managed, self.max_delete_percent
+            ));
+        }
+        Ok(())
+    }
+
+    /// whether blog path (or one of its directories) matches an ignore rule
+    pub fn is_ignored(&self, blog_path: &str) -> bool {
+        let patterns: Vec<Pattern> = self
+            .ignore
+            .iter()
+            .filter_map(|pattern| Pattern::new(pattern).ok())
+            .collect();
+        blog_path
+            .match_indices('/')
+            .map(|(i, _)| &blog_path[..i])
+            .chain(std::iter::once(blog_path))
+            .any(|path| patterns.iter().any(|pattern| pattern.matches(path)))
+    }
+}
+
+/// join relative path with current directory
+fn absolute_path(path: &str) -> PathBuf {
+    let path = std::env::current_dir().unwrap().join(path);
+    fs::canonicalize(path.as_path()).unwrap_or(path)
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_and_save() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(Settings::load(dir.path()).unwrap(), Settings::default());
+
+        let mut settings = Settings::default();
+        settings.pin_root_path(dir.path().to_str().unwrap());
+        settings.ignore.push("drafts/*".to_string());
+        settings.publish = false;
+        settings.delete_policy = DeletePolicy::Unpublish;
+        settings.save(dir.path());
+        assert_eq!(Settings::load(dir.path()).unwrap(), settings);
+
+        fs::write(dir.path().join(SETTINGS_CFG), "delete_policy = \"archive\"").unwrap();
+        assert_eq!(Settings::load(dir.path()).unwrap().delete_policy, DeletePolicy::Archive);
+
+        fs::write(dir.path().join(SETTINGS_CFG), "ignore = [\"[\"]").unwrap();
+        assert!(Settings::load(dir.path()).is_err());
+    }
+
+    #[test]
+    fn root_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let articles = dir.path().join("articles");
+        let mut settings = Settings::default();
+        assert!(settings.check_root_path(Some(dir.path().to_str().unwrap())).is_err());
+
+        settings.pin_root_path(articles.to_str().unwrap());
+        // doesn't exist
+        assert!(settings.check_root_path(None).is_err());
+
+        fs::create_dir(articles.as_path()).unwrap();
+        assert!(settings.check_root_path(None).is_ok());
+        assert!(settings.check_root_path(Some(articles.join(".").to_str().unwrap())).is_ok());
+        assert!(settings.check_root_path(Some(dir.path().to_str().unwrap())).is_err());
+    }
+
+    #[test]
+    fn mass_delete() {
+        let settings = Settings::default();
+        assert!(settings.check_mass_delete(1, 1).is_ok());
+        assert!(settings.check_mass_delete(3, 10).is_ok());
+        assert!(settings.check_mass_delete(4, 10).is_err());
+        assert!(settings.check_mass_delete(11, 1000).is_err());
+    }
+
+    #[test]
+    fn ignore_rules() {
+        let settings = Settings {
+            ignore: vec!["drafts/*".to_string(), "*.tmp.md".to_string()],
+            ..Default::default()
+        };
+        assert!(settings.is_ignored("drafts/a.md"));
+        assert!(settings.is_ignored("Rust/a.tmp.md"));
+        assert!(!settings.is_ignored("Rust/a.md"));
+
+        // a directory ignores everything in it
+        let settings = Settings {
+            ignore: vec!["private".to_string()],
+            ..Default::default()
+        };
+        assert!(settings.is_ignored("private/notes/a.md"));
+        assert!(!settings.is_ignored("privateer.md"));
+    }
+}
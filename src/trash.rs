@@ -0,0 +1,258 @@
+// `.cnblog_deleted` trash of deleted blogs, recorded by a manifest
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::meta_weblog::cfg::BlogsInfoDO;
+
+/// trash directory in root path
+pub const TRASH_DIR: &str = ".cnblog_deleted";
+const MANIFEST: &str = "manifest.json";
+
+/// a deleted blog in trash
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TrashEntry {
+    pub id: u32,
+    // file name in trash
+    pub file_name: String,
+    // original blog path
+    pub blog_path: String,
+    pub postid: i32,
+    // deletion timestamp
+    pub deleted_at: i64,
+    // hostname of device which moved the blog to trash
+    pub device: String,
+}
+
+pub struct Trash {
+    dir: PathBuf,
+    entries: Vec<TrashEntry>,
+}
+
+impl Trash {
+    /// open trash of root path, files moved by older cnblog aren't recorded
+    pub fn open(root_path: &str) -> Trash {
+        let dir = Path::new(root_path).join(TRASH_DIR);
+        let entries = match fs::read_to_string(dir.join(MANIFEST)) {
+            Ok(content) => serde_json::from_str(content.as_str()).unwrap_or_else(|e| {
+                eprintln!("Warning: trash manifest is broken, it's ignored. Error: {}", e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        Trash { dir, entries }
+    }
+
+    pub fn entries(&self) -> &[TrashEntry] {
+        &self.entries
+    }
+
+    /// move blog into trash and record it
+    pub fn put(&mut self, root_path: &str, blog_path: &str, postid: i32) -> io::Result<()> {
+        // 1. make sure trash exists
+        if !self.dir.exists() {
+            fs::create_dir(self.dir.as_path())?;
+        }
+        if !self.dir.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{:?} already exists but is not a directory", self.dir),
+            ));
+        }
+
+        // 2. move file, the new and old file need to be in same mount point
+        let (id, file_name) = self.next_file_name(blog_path);
+        fs::rename(Path::new(root_path).join(blog_path), self.dir.join(file_name.as_str()))?;
+
+        // 3. record
+        self.record(id, file_name, blog_path, postid)
+    }
+
+    /// save final content of a remote post which local blog is already removed
+    pub fn archive(&mut self, blog_path: &str, postid: i32, content: &str) -> io::Result<()> {
+        fs::create_dir_all(self.dir.as_path())?;
+        let (id, file_name) = self.next_file_name(blog_path);
+        fs::write(self.dir.join(file_name.as_str()), content)?;
+        self.record(id, file_name, blog_path, postid)
+    }
+
+    fn next_file_name(&self, blog_path: &str) -> (u32, String) {
+        let id = self.entries.iter().map(|entry| entry.id).max().unwrap_or(0) + 1;
+        let name = Path::new(blog_path).file_name().unwrap().to_str().unwrap();
+        (id, format!("{}_{}", id, name))
+    }
+
+    fn record(&mut self, id: u32, file_name: String, blog_path: &str, postid: i32) -> io::Result<()> {
+        self.entries.push(TrashEntry {
+            id,
+            file_name,
+            blog_path: blog_path.to_string(),
+            postid,
+            deleted_at: Local::now().timestamp(),
+            device: hostname(),
+        });
+        self.save()
+    }
+
+    /// move blog back to its original path and forget it
+    pub fn restore(&mut self, root_path: &str, id: u32) -> io::Result<TrashEntry> {
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.id == id)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no blog {} in trash", id)))?;
+        let entry = self.entries[index].clone();
+        let path = Path::new(root_path).join(entry.blog_path.as_str());
+        if path.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{:?} already exists", path),
+            ));
+        }
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::rename(self.dir.join(entry.file_name.as_str()), path.as_path())?;
+        self.entries.remove(index);
+        self.save()?;
+        Ok(entry)
+    }
+
+    /// delete blogs moved to trash before `timestamp`
+    pub fn purge(&mut self, timestamp: i64) -> io::Result<Vec<TrashEntry>> {
+        let (purged, kept): (Vec<TrashEntry>, Vec<TrashEntry>) = self
+            .entries
+            .drain(..)
+            .partition(|entry| entry.deleted_at < timestamp);
+        self.entries = kept;
+        for entry in purged.iter() {
+            let path = self.dir.join(entry.file_name.as_str());
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+        self.save()?;
+        Ok(purged)
+    }
+
+    fn save(&self) -> io::Result<()> {
+        fs::write(
+            self.dir.join(MANIFEST),
+            serde_json::to_string_pretty(&self.entries).unwrap(),
+        )
+    }
+}
+
+/// parse duration like `30d`, `12h`, `2w` to seconds, it must be positive
+pub fn parse_duration(duration: &str) -> Option<i64> {
+    let unit = duration.chars().last()?;
+    let seconds = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    let number: i64 = duration[..duration.len() - 1].parse().ok()?;
+    // a negative duration would purge everything
+    if number <= 0 {
+        return None;
+    }
+    number.checked_mul(seconds)
+}
+
+/// delete(move) file from root_path to trash
+pub fn delete_blogs_by_blogs_info(blogs_info: Vec<BlogsInfoDO>, root_path: &str) {
+    let mut trash = Trash::open(root_path);
+    for blog_info in blogs_info {
+        if let Err(e) = trash.put(root_path, blog_info.blog_path.as_str(), blog_info.postid) {
+            eprintln!(
+                "Warning: a error occurred while moving {} to {}. Error: {} ",
+                blog_info.blog_path, TRASH_DIR, e
+            );
+        }
+    }
+}
+
+/// hostname of this device
+fn hostname() -> String {
+    env::var("HOSTNAME")
+        .or_else(|_| env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn put_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let root_path = dir.path().to_str().unwrap();
+        fs::create_dir(dir.path().join("Rust")).unwrap();
+        fs::write(dir.path().join("Rust/a.md"), "a").unwrap();
+
+        let mut trash = Trash::open(root_path);
+        trash.put(root_path, "Rust/a.md", 42).unwrap();
+        assert!(!dir.path().join("Rust/a.md").exists());
+
+        // manifest is saved
+        let mut trash = Trash::open(root_path);
+        assert_eq!(trash.entries().len(), 1);
+        assert_eq!(trash.entries()[0].postid, 42);
+
+        fs::remove_dir(dir.path().join("Rust")).unwrap();
+        let entry = trash.restore(root_path, 1).unwrap();
+        assert_eq!(entry.blog_path, "Rust/a.md");
+        assert_eq!(fs::read_to_string(dir.path().join("Rust/a.md")).unwrap(), "a");
+        assert!(trash.restore(root_path, 1).is_err());
+    }
+
+    #[test]
+    fn archive_remote_post() {
+        let dir = tempfile::tempdir().unwrap();
+        let root_path = dir.path().to_str().unwrap();
+        let mut trash = Trash::open(root_path);
+        trash.archive("Rust/a.md", 42, "final").unwrap();
+
+        let trash_entry = Trash::open(root_path).entries()[0].clone();
+        assert_eq!(trash_entry.file_name, "1_a.md");
+        trash.restore(root_path, trash_entry.id).unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join("Rust/a.md")).unwrap(), "final");
+    }
+
+    #[test]
+    fn purge_old_blogs() {
+        let dir = tempfile::tempdir().unwrap();
+        let root_path = dir.path().to_str().unwrap();
+        fs::write(dir.path().join("a.md"), "a").unwrap();
+        fs::write(dir.path().join("b.md"), "b").unwrap();
+        let mut trash = Trash::open(root_path);
+        trash.put(root_path, "a.md", 1).unwrap();
+        trash.put(root_path, "b.md", 2).unwrap();
+        trash.entries[0].deleted_at -= 100;
+
+        let purged = trash.purge(Local::now().timestamp() - 50).unwrap();
+        assert_eq!(purged.len(), 1);
+        assert!(!dir.path().join(TRASH_DIR).join(purged[0].file_name.as_str()).exists());
+        assert_eq!(Trash::open(root_path).entries().len(), 1);
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("30d"), Some(30 * 24 * 60 * 60));
+        assert_eq!(parse_duration("2w"), Some(14 * 24 * 60 * 60));
+        assert_eq!(parse_duration("d"), None);
+        assert_eq!(parse_duration("10y"), None);
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("-5d"), None);
+        assert_eq!(parse_duration("0d"), None);
+    }
+}
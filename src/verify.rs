@@ -0,0 +1,446 @@
+// detect posts deleted or edited directly on the cnblog website
+use std::fs;
+use std::io::{stdin, stdout, Write};
+use std::path::Path;
+
+use crate::meta_weblog::cfg::{BlogsInfoDO, Config, Utility};
+use crate::meta_weblog::rpc::{MetaWeblog, RpcError};
+use crate::meta_weblog::weblog::Post;
+use crate::settings::DeletePolicy;
+use crate::trash::{self, Trash};
+
+/// how to resolve drift between remote post and blogs info
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Resolution {
+    /// accept remote state: save remote content or move local blog to trash
+    Pull,
+    /// upload local content to the remote post
+    Overwrite,
+    /// upload local content as a new post
+    Recreate,
+    /// do nothing
+    Skip,
+}
+
+/// drift found by verifying a blog
+enum Drift {
+    /// remote content was changed outside cnblog
+    Changed(Post),
+    /// remote post doesn't exist anymore
+    Missing,
+}
+
+/// Fetch every managed post and compare it with blogs info
+/// `changed` and `missing` resolve drift without asking, otherwise ask user
+/// `publish` is used while uploading local blogs
+/// return the number of drifted posts, a fault which can't be confirmed aborts verifying
+pub fn verify_remote_posts(
+    cfg: &Config,
+    weblog: &MetaWeblog,
+    root_path: &str,
+    publish: bool,
+    changed: Option<Resolution>,
+    missing: Option<Resolution>,
+) -> Result<usize, String> {
+    let blogs_info = cfg.get_local_existed_blogs();
+    println!("Info: verify {} remote posts.", blogs_info.len());
+
+    // remote posts are downloaded in batches
+    let postids: Vec<String> = blogs_info.iter().map(|blog_info| blog_info.postid.to_string()).collect();
+    let posts = weblog.get_posts(&postids);
+
+    let mut drift_count = 0;
+    for (blog_info, post) in blogs_info.into_iter().zip(posts) {
+        let local_path = Path::new(root_path).join(blog_info.blog_path.as_str());
+
+        // 1. find drift
+        let drift = match find_drift(cfg, weblog, &blog_info, local_path.as_path(), post)? {
+            Some(drift) => drift,
+            None => continue,
+        };
+        drift_count += 1;
+
+        // 2. resolve drift
+        let local_changed = local_path.exists()
+            && Utility::get_file_timestamp(local_path.as_path()) > blog_info.timestamp;
+        let resolution = match drift {
+            Drift::Changed(_) => {
+                println!("Remote changed: {} (postid: {})", blog_info.blog_path, blog_info.postid);
+                changed.unwrap_or_else(|| {
+                    ask_resolution(local_changed, &[Resolution::Pull, Resolution::Overwrite])
+                })
+            }
+            Drift::Missing => {
+                println!("Remote missing: {} (postid: {})", blog_info.blog_path, blog_info.postid);
+                missing.unwrap_or_else(|| {
+                    ask_resolution(local_changed, &[Resolution::Pull, Resolution::Recreate])
+                })
+            }
+        };
+        match (drift, resolution) {
+            (Drift::Changed(post), Resolution::Pull) => pull_post(cfg, &blog_info, root_path, post),
+            (Drift::Changed(_), Resolution::Overwrite) => overwrite_post(cfg, weblog, &blog_info, local_path.as_path(), publish),
+            (Drift::Missing, Resolution::Pull) => {
+                println!("Move {} to trash.", blog_info.blog_path);
+                let postid = blog_info.postid;
+                trash::delete_blogs_by_blogs_info(vec![blog_info], root_path);
+                cfg.delete_post(postid, DeletePolicy::Delete.state());
+            }
+            (Drift::Missing, Resolution::Recreate) => recreate_post(cfg, weblog, &blog_info, local_path.as_path(), publish),
+            _ => println!("Skipped."),
+        }
+    }
+    println!("Info: find {} drifted posts.", drift_count);
+    Ok(drift_count)
+}
+
+/// compare remote post with blogs info, None while there is no drift
+fn find_drift(
+    cfg: &Config,
+    weblog: &MetaWeblog,
+    blog_info: &BlogsInfoDO,
+    local_path: &Path,
+    post: Result<Post, RpcError>,
+) -> Result<Option<Drift>, String> {
+    match post {
+        Ok(post) => {
+            let remote_hash = Utility::content_hash(post.description.as_str());
+            let base_hash = match blog_info.hash.as_ref() {
+                Some(hash) => hash.clone(),
+                // recorded by older cnblog, unchanged local blog is the base
+                None => match unchanged_local_hash(blog_info, local_path) {
+                    Some(hash) => {
+                        if hash == remote_hash {
+                            cfg.set_post_hash(blog_info.postid, hash.as_str());
+                        }
+                        hash
+                    }
+                    None => return Ok(None),
+                },
+            };
+            if base_hash == remote_hash {
+                return Ok(None);
+            }
+            Ok(Some(Drift::Changed(post)))
+        }
+        Err(e) if e.fault().is_some() => confirm_missing(cfg, weblog, blog_info, local_path),
+        Err(e) => {
+            eprintln!("Warning: can't verify {}. Error: {}", blog_info.blog_path, e);
+            Ok(None)
+        }
+    }
+}
+
+/// ask the post alone again after a fault
+/// it's missing only if the fault is answered again and the account isn't rejected,
+/// other faults (rejected account, rate limit, server error) must not move a good blog to trash
+fn confirm_missing(
+    cfg: &Config,
+    weblog: &MetaWeblog,
+    blog_info: &BlogsInfoDO,
+    local_path: &Path,
+) -> Result<Option<Drift>, String> {
+    let fault = match weblog.get_post(blog_info.postid.to_string().as_str()) {
+        Ok(post) => return find_drift(cfg, weblog, blog_info, local_path, Ok(post)),
+        Err(e) if e.fault().is_some() => e,
+        Err(e) => {
+            eprintln!("Warning: can't verify {}. Error: {}", blog_info.blog_path, e);
+            return Ok(None);
+        }
+    };
+    if cfg.is_account_rejected() {
+        return Err(format!(
+            "cnblog rejects requests while verifying {}, nothing is moved to trash. Error: {}",
+            blog_info.blog_path, fault
+        ));
+    }
+    Ok(Some(Drift::Missing))
+}
+
+/// get hash of local blog if it isn't changed after last sync
+fn unchanged_local_hash(blog_info: &BlogsInfoDO, local_path: &Path) -> Option<String> {
+    if !local_path.exists() || Utility::get_file_timestamp(local_path) > blog_info.timestamp {
+        return None;
+    }
+    let content = fs::read_to_string(local_path).ok()?;
+    Some(Utility::content_hash(content.as_str()))
+}
+
+/// save remote content to local blog
+/// unsynced local changes are archived into trash first, they aren't saved anywhere else
+fn pull_post(cfg: &Config, blog_info: &BlogsInfoDO, root_path: &str, post: Post) {
+    let local_path = Path::new(root_path).join(blog_info.blog_path.as_str());
+    let local_path = local_path.as_path();
+    if let Some(local) = unsynced_local_content(blog_info, local_path).filter(|local| *local != post.description) {
+        let blog_path = blog_info.blog_path.as_str();
+        if let Err(e) = Trash::open(root_path).archive(blog_path, blog_info.postid, local.as_str()) {
+            eprintln!("Warning: can't move local changes of {} to {}, it isn't pulled. Error: {}", blog_path, trash::TRASH_DIR, e);
+            return;
+        }
+        println!("Info: local changes of {} are moved to {}.", blog_path, trash::TRASH_DIR);
+    }
+    fs::create_dir_all(local_path.parent().unwrap()).unwrap();
+    fs::write(local_path, post.description.as_str()).unwrap();
+    let timestamp = Utility::get_file_timestamp(local_path);
+    let hash = Utility::content_hash(post.description.as_str());
+    cfg.edit_post(blog_info.postid, timestamp, hash.as_str());
+    cfg.cache_post(blog_info.postid, post.description.as_str());
+    println!("Pulled: {}", blog_info.blog_path);
+}
+
+/// content of local blog if it's changed after last sync
+fn unsynced_local_content(blog_info: &BlogsInfoDO, local_path: &Path) -> Option<String> {
+    let content = fs::read_to_string(local_path).ok()?;
+    let changed = match blog_info.hash.as_ref() {
+        Some(hash) => Utility::content_hash(content.as_str()) != *hash,
+        // recorded by older cnblog
+        None => Utility::get_file_timestamp(local_path) > blog_info.timestamp,
+    };
+    changed.then_some(content)
+}
+
+/// upload local content to the remote post
+fn overwrite_post(cfg: &Config, weblog: &MetaWeblog, blog_info: &BlogsInfoDO, local_path: &Path, publish: bool) {
+    let post = match local_post(local_path) {
+        Some(post) => post,
+        None => return,
+    };
+    let content = post.description.clone();
+    if let Err(e) = weblog.edit_post(blog_info.postid.to_string().as_str(), post, publish) {
+        eprintln!("Warning: can't overwrite {}. Error: {}", blog_info.blog_path, e);
+        return;
+    }
+    let hash = Utility::content_hash(content.as_str());
+    cfg.edit_post(blog_info.postid, Utility::get_file_timestamp(local_path), hash.as_str());
+    cfg.cache_post(blog_info.postid, content.as_str());
+    println!("Overwritten: {}", blog_info.blog_path);
+}
+
+/// upload local content as a new post and record the new postid
+fn recreate_post(cfg: &Config, weblog: &MetaWeblog, blog_info: &BlogsInfoDO, local_path: &Path, publish: bool) {
+    let mut post = match local_post(local_path) {
+        Some(post) => post,
+        None => return,
+    };
+    post.categories.push("[Markdown]".to_string());
+    let content = post.description.clone();
+    let postid = match weblog.new_post(post, publish) {
+        Ok(postid) => postid.parse().unwrap(),
+        Err(e) => {
+            eprintln!("Warning: can't re-create {}. Error: {}", blog_info.blog_path, e);
+            return;
+        }
+    };
+    let hash = Utility::content_hash(content.as_str());
+    cfg.replace_postid(blog_info.postid, postid, Utility::get_file_timestamp(local_path), hash.as_str());
+    cfg.cache_post(postid, content.as_str());
+    println!("Re-created: {} (postid: {})", blog_info.blog_path, postid);
+}
+
+/// generate post from local blog like uploading it
+fn local_post(local_path: &Path) -> Option<Post> {
+    let content = match fs::read_to_string(local_path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Warning: can't read {:?}. Error: {}", local_path, e);
+            return None;
+        }
+    };
+    let category = local_path.parent().unwrap().file_name().unwrap().to_str().unwrap().to_string();
+    Some(Post {
+        description: content,
+        categories: vec![category],
+        title: local_path.file_name().unwrap().to_str().unwrap().to_string(),
+        ..Default::default()
+    })
+}
+
+/// ask user how to resolve drift
+fn ask_resolution(local_changed: bool, choices: &[Resolution]) -> Resolution {
+    if local_changed {
+        println!("Warning: local blog is changed too, pulling will move local changes to {}!", trash::TRASH_DIR);
+    }
+    let prompt: Vec<&str> = choices
+        .iter()
+        .map(|choice| match choice {
+            Resolution::Pull => "[p]ull",
+            Resolution::Overwrite => "[o]verwrite",
+            Resolution::Recreate => "[r]e-create",
+            Resolution::Skip => "[s]kip",
+        })
+        .collect();
+    let mut buf = String::new();
+    loop {
+        print!("{} or [s]kip? ", prompt.join(", "));
+        stdout().flush().unwrap();
+        buf.clear();
+        stdin().read_line(&mut buf).unwrap();
+        let resolution = match buf.trim() {
+            "p" => Resolution::Pull,
+            "o" => Resolution::Overwrite,
+            "r" => Resolution::Recreate,
+            "s" | "" => Resolution::Skip,
+            _ => continue,
+        };
+        if resolution == Resolution::Skip || choices.contains(&resolution) {
+            return resolution;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use tempfile::TempDir;
+    use xmlrpc::Fault;
+
+    use super::{find_drift, verify_remote_posts, Drift, Resolution};
+    use crate::meta_weblog::cfg::{BlogsInfoDO, Config, Utility, BLOGS_INFO_CFG};
+    use crate::meta_weblog::rpc::mock::{fault, local_weblog, post_content, response, serve};
+    use crate::meta_weblog::rpc::RpcError;
+    use crate::meta_weblog::weblog::Post;
+    use crate::trash::{Trash, TRASH_DIR};
+
+    const USERS_BLOGS: &str = "<array><data></data></array>";
+    const METHODS: &str = "<array><data><value><string>metaWeblog.getPost</string></value></data></array>";
+
+    /// config with a local database, its requests go to `endpoint`
+    fn local_config(dir: &TempDir, endpoint: &str) -> Config {
+        Config::init_blogs_cfg(dir.path().join(BLOGS_INFO_CFG).as_path()).unwrap();
+        let mut cfg = Config::new("user", "password", "app", 0, "1", dir.path().to_str().unwrap());
+        cfg.set_endpoint(endpoint);
+        cfg.open_local_conn().unwrap();
+        cfg
+    }
+
+    /// save `Rust/<name>` with `content`, it was synced with `synced` as `postid`
+    fn synced_blog(cfg: &Config, root: &Path, name: &str, postid: i32, content: &str, synced: &str) {
+        let blog_path = format!("Rust/{name}");
+        fs::create_dir_all(root.join("Rust")).unwrap();
+        fs::write(root.join(blog_path.as_str()), content).unwrap();
+        let timestamp = Utility::get_file_timestamp(root.join(blog_path.as_str()).as_path());
+        cfg.new_post(blog_path.as_str(), postid, timestamp, Utility::content_hash(synced).as_str());
+    }
+
+    fn blog_info(cfg: &Config, postid: i32) -> Option<BlogsInfoDO> {
+        cfg.get_local_existed_blogs().into_iter().find(|blog_info| blog_info.postid == postid)
+    }
+
+    fn remote_post(description: &str) -> Result<Post, RpcError> {
+        Ok(Post { description: description.to_string(), ..Default::default() })
+    }
+
+    fn post_fault() -> Result<Post, RpcError> {
+        let fault = Fault { fault_code: 1, fault_string: "post not found".to_string() };
+        Err(RpcError::Fault { method: "metaWeblog.getPost".to_string(), fault })
+    }
+
+    #[test]
+    fn classify_drift() {
+        let (config_dir, root) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let (endpoint, server) = serve(vec![fault(), response(USERS_BLOGS)]);
+        let cfg = local_config(&config_dir, endpoint.as_str());
+        let weblog = local_weblog(endpoint.as_str());
+        synced_blog(&cfg, root.path(), "a.md", 1, "synced", "synced");
+        let blog_info = blog_info(&cfg, 1).unwrap();
+        let local_path = root.path().join("Rust/a.md");
+
+        let drift = |post| find_drift(&cfg, &weblog, &blog_info, local_path.as_path(), post).unwrap();
+        assert!(drift(remote_post("synced")).is_none());
+        assert!(matches!(drift(remote_post("edited")), Some(Drift::Changed(post)) if post.description == "edited"));
+        // the post is asked again and the account is accepted
+        assert!(matches!(drift(post_fault()), Some(Drift::Missing)));
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains("metaWeblog.getPost"));
+        assert!(requests[1].contains("blogger.getUsersBlogs"));
+    }
+
+    #[test]
+    fn rejected_account_aborts() {
+        let (config_dir, root) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let (endpoint, server) = serve(vec![fault(), fault(), fault()]);
+        let cfg = local_config(&config_dir, endpoint.as_str());
+        let weblog = local_weblog(endpoint.as_str());
+        synced_blog(&cfg, root.path(), "a.md", 1, "synced", "synced");
+
+        let root_path = root.path().to_str().unwrap();
+        let result = verify_remote_posts(&cfg, &weblog, root_path, false, None, Some(Resolution::Pull));
+        assert!(result.is_err());
+        // the blog is kept
+        assert!(root.path().join("Rust/a.md").exists());
+        assert!(blog_info(&cfg, 1).is_some());
+        assert_eq!(server.join().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn pull_changed_and_missing() {
+        let (config_dir, root) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let responses = vec![
+            response(METHODS),
+            response(post_content("a.md", "edited").as_str()),
+            fault(),
+            fault(),
+            response(USERS_BLOGS),
+        ];
+        let (endpoint, server) = serve(responses);
+        let cfg = local_config(&config_dir, endpoint.as_str());
+        let weblog = local_weblog(endpoint.as_str());
+        // local changes of a.md aren't synced
+        synced_blog(&cfg, root.path(), "a.md", 1, "local", "synced");
+        synced_blog(&cfg, root.path(), "b.md", 2, "synced", "synced");
+
+        let root_path = root.path().to_str().unwrap();
+        let pull = Some(Resolution::Pull);
+        assert_eq!(verify_remote_posts(&cfg, &weblog, root_path, false, pull, pull), Ok(2));
+        assert_eq!(fs::read_to_string(root.path().join("Rust/a.md")).unwrap(), "edited");
+        assert_eq!(blog_info(&cfg, 1).unwrap().hash.unwrap(), Utility::content_hash("edited"));
+        assert!(!root.path().join("Rust/b.md").exists());
+        let trash = Trash::open(root_path);
+        let blog_paths: Vec<&str> = trash.entries().iter().map(|entry| entry.blog_path.as_str()).collect();
+        assert_eq!(blog_paths, ["Rust/a.md", "Rust/b.md"]);
+        let archived = root.path().join(TRASH_DIR).join(trash.entries()[0].file_name.as_str());
+        assert_eq!(fs::read_to_string(archived).unwrap(), "local");
+        assert!(blog_info(&cfg, 2).is_none());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn overwrite_changed() {
+        let (config_dir, root) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let responses = vec![response(post_content("a.md", "edited").as_str()), response("<boolean>1</boolean>")];
+        let (endpoint, server) = serve(responses);
+        let cfg = local_config(&config_dir, endpoint.as_str());
+        let weblog = local_weblog(endpoint.as_str());
+        synced_blog(&cfg, root.path(), "a.md", 1, "local", "synced");
+
+        let root_path = root.path().to_str().unwrap();
+        let result = verify_remote_posts(&cfg, &weblog, root_path, false, Some(Resolution::Overwrite), None);
+        assert_eq!(result, Ok(1));
+        assert_eq!(blog_info(&cfg, 1).unwrap().hash.unwrap(), Utility::content_hash("local"));
+
+        let requests = server.join().unwrap();
+        assert!(requests[1].contains("metaWeblog.editPost") && requests[1].contains("local"));
+    }
+
+    #[test]
+    fn recreate_missing() {
+        let (config_dir, root) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let responses = vec![fault(), fault(), response(USERS_BLOGS), response("<string>9</string>")];
+        let (endpoint, server) = serve(responses);
+        let cfg = local_config(&config_dir, endpoint.as_str());
+        let weblog = local_weblog(endpoint.as_str());
+        synced_blog(&cfg, root.path(), "a.md", 1, "local", "local");
+
+        let root_path = root.path().to_str().unwrap();
+        let result = verify_remote_posts(&cfg, &weblog, root_path, false, None, Some(Resolution::Recreate));
+        assert_eq!(result, Ok(1));
+        assert!(blog_info(&cfg, 1).is_none());
+        assert_eq!(blog_info(&cfg, 9).unwrap().blog_path, "Rust/a.md");
+
+        let requests = server.join().unwrap();
+        assert!(requests[3].contains("metaWeblog.newPost") && requests[3].contains("local"));
+    }
+}
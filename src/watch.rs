@@ -0,0 +1,139 @@
+// watch mode: sync while blogs in root path are changed, and poll remote changes
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Component, Path};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
+
+use crate::settings::Settings;
+
+/// watch root path and call `sync` until the watcher is stopped
+/// `sync` is called after blogs are changed and saves are settled for `debounce`,
+/// or if nothing is changed for `interval` to pull remote changes
+/// a failed sync doesn't stop watching, it's retried by the next change or poll
+pub fn watch<F: FnMut()>(
+    root_path: &str,
+    settings: &Settings,
+    debounce: Duration,
+    interval: Duration,
+    mut sync: F,
+) -> Result<(), String> {
+    // 1. watch root path recursively (inotify on linux)
+    let (tx, rx) = channel();
+    let mut watcher = watcher(tx, debounce).map_err(|e| format!("can't watch {}: {}", root_path, e))?;
+    watcher
+        .watch(root_path, RecursiveMode::Recursive)
+        .map_err(|e| format!("can't watch {}: {}", root_path, e))?;
+    println!("Info: watching {}, press Ctrl-C to stop.", root_path);
+
+    // 2. sync on changes, poll remote on timeout
+    let mut last_sync = Instant::now();
+    let mut changed = false;
+    loop {
+        let timeout = if changed { Duration::ZERO } else { interval.saturating_sub(last_sync.elapsed()) };
+        match rx.recv_timeout(timeout) {
+            Ok(event) if !is_blog_event(&event, root_path, settings) => continue,
+            Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Err("watcher is stopped".to_string()),
+        }
+        // upload paths panic on network errors or files removed while saving
+        if panic::catch_unwind(AssertUnwindSafe(&mut sync)).is_err() {
+            eprintln!("Warning: sync failed, it will be retried.");
+        }
+        // blogs saved meanwhile are synced again at once,
+        // files written by sync itself (pulled blogs, trash) are found unchanged by it
+        changed = drain(&rx, debounce, root_path, settings);
+        last_sync = Instant::now();
+    }
+}
+
+/// receive events which arrive in `debounce`, return whether a blog is changed
+fn drain(rx: &Receiver<DebouncedEvent>, debounce: Duration, root_path: &str, settings: &Settings) -> bool {
+    let deadline = Instant::now() + debounce * 2;
+    let mut changed = false;
+    while let Ok(event) = rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+        changed |= is_blog_event(&event, root_path, settings);
+    }
+    changed
+}
+
+/// whether event changes a synced blog
+fn is_blog_event(event: &DebouncedEvent, root_path: &str, settings: &Settings) -> bool {
+    match event {
+        DebouncedEvent::Create(path) | DebouncedEvent::Write(path) | DebouncedEvent::Remove(path) => {
+            is_blog_path(path, root_path, settings)
+        }
+        DebouncedEvent::Rename(from, to) => {
+            is_blog_path(from, root_path, settings) || is_blog_path(to, root_path, settings)
+        }
+        // some events are lost, so everything may be changed
+        DebouncedEvent::Rescan => true,
+        DebouncedEvent::Error(e, path) => {
+            eprintln!("Warning: watch error on {:?}. Error: {}", path, e);
+            false
+        }
+        DebouncedEvent::NoticeWrite(_) | DebouncedEvent::NoticeRemove(_) | DebouncedEvent::Chmod(_) => false,
+    }
+}
+
+/// markdown (or a removed directory) in root path which isn't hidden or ignored
+fn is_blog_path(path: &Path, root_path: &str, settings: &Settings) -> bool {
+    let root = Path::new(root_path);
+    // events may use canonical path
+    let relative = match path.strip_prefix(root).ok().map(Path::to_path_buf).or_else(|| {
+        let root = root.canonicalize().ok()?;
+        path.strip_prefix(root).ok().map(Path::to_path_buf)
+    }) {
+        Some(relative) => relative,
+        None => return false,
+    };
+    let hidden = relative.components().any(|component| match component {
+        Component::Normal(name) => name.to_str().is_none_or(|name| name.starts_with('.')),
+        _ => false,
+    });
+    if hidden || relative.as_os_str().is_empty() {
+        return false;
+    }
+    let is_markdown = match relative.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => ext == "md" || ext == "markdown",
+        // directory, it's removed or renamed with its blogs
+        None => true,
+    };
+    let blog_path = relative.to_str().unwrap().replace('\\', "/");
+    is_markdown && !settings.is_ignored(blog_path.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blog_paths() {
+        let settings = Settings {
+            ignore: vec!["drafts".to_string()],
+            ..Default::default()
+        };
+        let is_blog = |path: &str| is_blog_path(Path::new(path), "/articles", &settings);
+        assert!(is_blog("/articles/Rust/a.md"));
+        assert!(is_blog("/articles/Rust"));
+        assert!(!is_blog("/articles/Rust/a.png"));
+        assert!(!is_blog("/articles/Rust/.a.md.swp"));
+        assert!(!is_blog("/articles/.cnblog_deleted/1_a.md"));
+        assert!(!is_blog("/articles/drafts/a.md"));
+        assert!(!is_blog("/other/a.md"));
+    }
+
+    #[test]
+    fn drain_keeps_changes() {
+        let settings = Settings::default();
+        let (tx, rx) = channel();
+        tx.send(DebouncedEvent::Chmod("/articles/Rust/a.md".into())).unwrap();
+        assert!(!drain(&rx, Duration::from_millis(10), "/articles", &settings));
+
+        tx.send(DebouncedEvent::Write("/articles/Rust/a.png".into())).unwrap();
+        tx.send(DebouncedEvent::Write("/articles/Rust/a.md".into())).unwrap();
+        assert!(drain(&rx, Duration::from_millis(10), "/articles", &settings));
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,6 @@
+// compile errors of Convert derive
+#[test]
+fn convert_compile_errors() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
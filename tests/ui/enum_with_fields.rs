@@ -0,0 +1,9 @@
+use cnblog::Convert;
+
+#[derive(Convert)]
+enum State {
+    Published,
+    Deleted(String),
+}
+
+fn main() {}
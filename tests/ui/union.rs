@@ -0,0 +1,9 @@
+use cnblog::Convert;
+
+#[derive(Convert)]
+union Number {
+    int: i32,
+    double: f64,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use cnblog::Convert;
+
+#[derive(Default, Convert)]
+struct Post {
+    #[convert(rename_to = "postid")]
+    id: String,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use cnblog::Convert;
+
+#[derive(Default, Convert)]
+struct Post {
+    title: String,
+    views: u32,
+}
+
+fn main() {}